@@ -0,0 +1,1160 @@
+//! The interactive sandbox window.
+
+use std::{cell::RefCell, io, rc::Rc, sync::Arc};
+
+use winit::{dpi::PhysicalSize, keyboard::KeyCode};
+use winit_input_helper::WinitInputHelper;
+
+use crate::{
+    autosave::Autosave,
+    bbox::BoundingBox,
+    brush::Brush,
+    camera::Camera,
+    cell::{Cell, CellGrid, CellType},
+    chunk::{CHUNK_HEIGHT, CHUNK_WIDTH},
+    fill, hash,
+    image::{self, ColorTable},
+    palette::Palette,
+    pattern::Pattern,
+    raster,
+    region::RegionStore,
+    render::{
+        pipeline::RenderPipeline2D,
+        renderer::{Frame, RenderTarget, Renderer, Screenshot},
+    },
+    replay::{self, EditRecorder, Event, Recording, Replay},
+    save,
+    stamp::StampLibrary,
+    timelapse::Timelapse,
+    tool::Tool,
+    window::{Application, WindowManager},
+    world::World,
+    world_renderer::WorldRenderer,
+};
+
+/// Options for [`run`].
+#[derive(Clone, Copy, Default)]
+pub struct Options {
+    /// Logs the world hash whenever the tick is a multiple of this interval.
+    pub hash_interval: Option<usize>,
+    /// Only simulates the chunks in view while no recording or replay is running.
+    pub simulate_visible_only: bool,
+    /// Renders with the fallback adapter, usually a software renderer.
+    pub force_fallback_adapter: bool,
+}
+
+/// Opens the sandbox window and runs it until the window is closed.
+///
+/// Fails if no graphics adapter can draw to the window or the region directory used to
+/// stream the world cannot be opened.
+pub fn run(options: Options) -> io::Result<()> {
+    let window_manager = WindowManager::new(WINDOW_TITLE, WINDOW_SIZE);
+    let app = FallingSandApplication::new(window_manager.window.clone(), options)?;
+    window_manager.run(Box::new(app));
+    Ok(())
+}
+
+const WINDOW_TITLE: &str = "Falling Sand";
+const WINDOW_SIZE: (u32, u32) = (800, 600);
+const REGIONS_DIRECTORY: &str = "regions";
+const LOADING_REGIONS_DIRECTORY: &str = "regions.loading";
+const MIN_STREAM_RADIUS: usize = 4;
+const CAMERA_PAN_SPEED: f32 = 400.0;
+const STAMPS_DIRECTORY: &str = "stamps";
+const IMPORT_COLORS_FILE: &str = "colors.txt";
+const EXPORTS_DIRECTORY: &str = "exports";
+const SAVE_FILE: &str = "world.fsw";
+const TIMELAPSE_INTERVAL: usize = 5;
+const RECORDING_FILE: &str = "recording.fsrec";
+const AUTOSAVE_FILE: &str = "autosave.fsw";
+const SESSION_LOCK_FILE: &str = "session.lock";
+const AUTOSAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+enum FrameSource {
+    Renderer,
+    Cells((i32, i32), (i32, i32)),
+}
+
+struct FallingSandApplication {
+    window: Arc<winit::window::Window>,
+    title: String,
+    renderer: Rc<RefCell<Renderer>>,
+    render_pipeline: RenderPipeline2D,
+
+    last_update: std::time::Instant,
+
+    camera: Camera,
+    ui_pipeline: RenderPipeline2D,
+    ui_camera: Camera,
+    palette: Palette,
+
+    world: World,
+    world_renderer: WorldRenderer,
+    regions: RegionStore,
+
+    tool: Tool,
+    brush: Brush,
+    cursor_cell: Option<(i32, i32)>,
+    last_cursor_cell: Option<(i32, i32)>,
+    last_paint_cell: Option<(i32, i32)>,
+    shape_start: Option<((i32, i32), bool)>,
+    fill_shapes: bool,
+    inspecting: bool,
+    selection: Option<((i32, i32), (i32, i32))>,
+    clipboard: Option<Pattern>,
+    stamps: StampLibrary,
+
+    autosave: Autosave,
+    recovery_pending: bool,
+
+    recording: Option<Recording>,
+    replay: Option<Replay>,
+    hash_interval: Option<usize>,
+    simulate_visible_only: bool,
+    screenshot_requested: bool,
+    timelapse: Option<(Timelapse, FrameSource)>,
+    timelapse_frame_requested: bool,
+}
+
+impl FallingSandApplication {
+    fn new(window: Arc<winit::window::Window>, options: Options) -> io::Result<Self> {
+        let renderer = Rc::new(RefCell::new(pollster::block_on(Renderer::new(
+            RenderTarget::Window(window.clone()),
+            options.force_fallback_adapter,
+        ))?));
+
+        let autosave = Autosave::new(AUTOSAVE_FILE, SESSION_LOCK_FILE, AUTOSAVE_INTERVAL);
+        let recovery_pending = autosave.begin_session().unwrap_or_else(|error| {
+            eprintln!("Failed to create {:?}: {}", SESSION_LOCK_FILE, error);
+            false
+        });
+        if recovery_pending {
+            println!(
+                "The last session did not exit cleanly, press Y to restore {:?} or N to discard it",
+                AUTOSAVE_FILE
+            );
+        }
+
+        // The regions of an unclean session are kept until the player decides whether to
+        // restore it, and the world stays paused until then.
+        let regions = RegionStore::new(REGIONS_DIRECTORY)?;
+        if !recovery_pending {
+            regions.clear()?;
+        }
+        let world = World::streamed(regions.clone(), 0, rand::random());
+        let world_renderer = WorldRenderer::new();
+
+        let size = window.inner_size();
+        let scale_factor = window.scale_factor() as f32;
+        let mut camera = Camera::new(size.width as f32, size.height as f32);
+        camera.set_scale_factor(scale_factor);
+        let (view_width, view_height) = camera.view_size();
+        camera.position.x = -view_width / 2.0;
+        camera.position.y = -view_height / 2.0;
+
+        let mut render_pipeline = RenderPipeline2D::new(renderer.clone());
+        render_pipeline.update_camera(&camera);
+
+        let mut ui_camera = Camera::new(size.width as f32, size.height as f32);
+        ui_camera.set_scale_factor(scale_factor);
+        let mut ui_pipeline = RenderPipeline2D::new(renderer.clone());
+        ui_pipeline.update_camera(&ui_camera);
+        let palette = Palette::new(&ui_pipeline, ui_camera.view_size().1);
+
+        let mut app = Self {
+            window,
+            title: WINDOW_TITLE.to_string(),
+            renderer,
+            render_pipeline,
+
+            last_update: std::time::Instant::now(),
+
+            camera,
+            ui_pipeline,
+            ui_camera,
+            palette,
+
+            world,
+            world_renderer,
+            regions,
+
+            tool: Tool::Brush,
+            brush: Brush::new(),
+            cursor_cell: None,
+            last_cursor_cell: None,
+            last_paint_cell: None,
+            shape_start: None,
+            fill_shapes: false,
+            inspecting: false,
+            selection: None,
+            clipboard: None,
+            stamps: Self::load_stamps(),
+
+            autosave,
+            recovery_pending,
+
+            recording: None,
+            replay: None,
+            hash_interval: options.hash_interval,
+            simulate_visible_only: options.simulate_visible_only,
+            screenshot_requested: false,
+            timelapse: None,
+            timelapse_frame_requested: false,
+        };
+        if !app.recovery_pending {
+            app.stream_world();
+        }
+        Ok(app)
+    }
+
+    fn read_world_into_regions(
+        &self,
+        read: impl FnOnce(RegionStore) -> io::Result<World>,
+    ) -> io::Result<World> {
+        let loading = RegionStore::new(LOADING_REGIONS_DIRECTORY)?;
+        let world = read(loading.clone())?;
+        self.regions.replace_with(loading)?;
+        Ok(World::streamed(
+            self.regions.clone(),
+            world.tick(),
+            world.seed(),
+        ))
+    }
+
+    fn stream_world(&mut self) {
+        let size = self.window.inner_size();
+        let center = self
+            .camera
+            .window_pos_to_world_pos((size.width as f32 / 2.0, size.height as f32 / 2.0).into());
+        let center = self.world_renderer.world_pos_to_cell(center);
+        let radius = self.stream_radius(center);
+        if let Some(recording) = &mut self.recording {
+            recording.record(self.world.tick(), Event::Stream { center, radius });
+        }
+
+        if let Err(error) = self.world.stream(center, radius) {
+            eprintln!(
+                "Failed to stream chunks from {:?}: {}",
+                REGIONS_DIRECTORY, error
+            );
+        }
+    }
+
+    /// Returns how many chunks around the center chunk to load so that every visible chunk, and
+    /// the neighbours it needs to be simulated, are loaded at the current zoom.
+    fn stream_radius(&self, center: (i32, i32)) -> usize {
+        let (from, to) = self
+            .world_renderer
+            .visible_cells(&self.camera.visible_bbox());
+        let chunk_x = |x: i32| x.div_euclid(CHUNK_WIDTH as i32);
+        let chunk_y = |y: i32| y.div_euclid(CHUNK_HEIGHT as i32);
+        let (center_x, center_y) = (chunk_x(center.0), chunk_y(center.1));
+        let visible = [
+            center_x - chunk_x(from.0),
+            chunk_x(to.0) - center_x,
+            center_y - chunk_y(from.1),
+            chunk_y(to.1) - center_y,
+        ];
+        let radius = visible.into_iter().max().unwrap_or(0).max(0) as usize + 1;
+        radius.clamp(MIN_STREAM_RADIUS, replay::MAX_STREAM_RADIUS)
+    }
+
+    fn set_world(&mut self, world: World) {
+        self.stop_recording();
+
+        self.world_renderer = WorldRenderer::new();
+        self.world = world;
+        self.stream_world();
+
+        self.selection = None;
+        self.shape_start = None;
+        self.last_paint_cell = None;
+    }
+
+    fn save_world(&self) {
+        match save::save_world(&self.world, SAVE_FILE) {
+            Ok(()) => println!("Saved world to {:?}", SAVE_FILE),
+            Err(error) => eprintln!("Failed to save {:?}: {}", SAVE_FILE, error),
+        }
+    }
+
+    fn load_world(&mut self, path: &std::path::Path) -> bool {
+        match self.read_world_into_regions(|regions| save::load_world_into(path, regions)) {
+            Ok(world) => {
+                self.set_world(world);
+                println!("Loaded world from {:?}", path);
+                true
+            }
+            Err(error) => {
+                eprintln!("Failed to load {:?}: {}", path, error);
+                false
+            }
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if self.recording.is_some() {
+            self.stop_recording();
+            return;
+        }
+
+        match Recording::start(&self.world) {
+            Ok(recording) => {
+                self.recording = Some(recording);
+                self.stream_world();
+                println!("Started recording on tick {}", self.world.tick());
+            }
+            Err(error) => eprintln!("Failed to start recording: {}", error),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+
+        match recording.save(RECORDING_FILE) {
+            Ok(()) => println!(
+                "Saved {} recorded inputs to {:?}",
+                recording.inputs().len(),
+                RECORDING_FILE
+            ),
+            Err(error) => eprintln!("Failed to save {:?}: {}", RECORDING_FILE, error),
+        }
+    }
+
+    fn start_replay(&mut self) {
+        let recording = match Recording::load(RECORDING_FILE) {
+            Ok(recording) => recording,
+            Err(error) => {
+                eprintln!("Failed to load {:?}: {}", RECORDING_FILE, error);
+                return;
+            }
+        };
+
+        match self.read_world_into_regions(|regions| recording.world_into(regions)) {
+            Ok(world) => {
+                self.set_world(world);
+                if let Err(error) = self.world.set_loaded(recording.loaded()) {
+                    eprintln!(
+                        "Failed to stream chunks from {:?}: {}",
+                        REGIONS_DIRECTORY, error
+                    );
+                    return;
+                }
+                self.replay = Some(Replay::new(&recording));
+                println!(
+                    "Replaying {} inputs from {:?}",
+                    recording.inputs().len(),
+                    RECORDING_FILE
+                );
+            }
+            Err(error) => eprintln!("Failed to load {:?}: {}", RECORDING_FILE, error),
+        }
+    }
+
+    fn log_hash(&self) {
+        match hash::world_hash(&self.world) {
+            Ok(hash) => println!("Tick {} hash {hash:016x}", self.world.tick()),
+            Err(error) => eprintln!("Failed to hash tick {}: {}", self.world.tick(), error),
+        }
+    }
+
+    fn handle_recovery(&mut self, input: &WinitInputHelper) {
+        // The regions still hold the crashed session, so they must be replaced or cleared before
+        // the prompt goes away.
+        if input.key_pressed(KeyCode::KeyY) {
+            self.recovery_pending =
+                !(self.load_world(std::path::Path::new(AUTOSAVE_FILE)) || self.start_new_world());
+        } else if input.key_pressed(KeyCode::KeyN) {
+            self.recovery_pending = !self.start_new_world();
+        }
+    }
+
+    fn start_new_world(&mut self) -> bool {
+        match self.regions.clear() {
+            Ok(()) => {
+                self.set_world(World::streamed(self.regions.clone(), 0, rand::random()));
+                true
+            }
+            Err(error) => {
+                eprintln!("Failed to clear {:?}: {}", REGIONS_DIRECTORY, error);
+                false
+            }
+        }
+    }
+
+    fn paint(&mut self, from: (i32, i32), to: (i32, i32), cell: Option<Cell>) {
+        let mut grid = EditRecorder::new(&mut self.world, self.recording.as_mut());
+        for center in raster::line(from, to) {
+            for (x, y) in self.brush.cells(center) {
+                grid.set_cell(x, y, cell);
+            }
+        }
+    }
+
+    fn overlay(&self) -> Vec<((i32, i32), [u8; 4])> {
+        let Some(cursor_cell) = self.cursor_cell else {
+            return Vec::new();
+        };
+
+        if self.inspecting {
+            return vec![(cursor_cell, [255, 255, 0, 255])];
+        }
+
+        match self.tool {
+            Tool::Brush => self
+                .brush
+                .outline(cursor_cell)
+                .into_iter()
+                .map(|cell| (cell, [200, 200, 200, 255]))
+                .collect(),
+            Tool::Fill => vec![(cursor_cell, [200, 200, 200, 255])],
+            Tool::Line | Tool::Rectangle | Tool::Circle => match self.shape_start {
+                Some((start, _)) => self
+                    .tool
+                    .shape_cells(start, cursor_cell, self.fill_shapes)
+                    .into_iter()
+                    .map(|cell| (cell, [200, 200, 200, 255]))
+                    .collect(),
+                None => vec![(cursor_cell, [200, 200, 200, 255])],
+            },
+            Tool::Select => match self.selection {
+                Some((from, to)) => raster::rectangle(from, to, false)
+                    .into_iter()
+                    .map(|cell| (cell, [0, 200, 255, 255]))
+                    .collect(),
+                None => vec![(cursor_cell, [200, 200, 200, 255])],
+            },
+            Tool::Paste | Tool::Stamp => match self.active_pattern() {
+                Some(pattern) => pattern
+                    .cells(Self::paste_origin(pattern, cursor_cell))
+                    .map(|(cell, cell_type)| (cell, cell_type.color()))
+                    .collect(),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    fn load_stamps() -> StampLibrary {
+        let stamps = StampLibrary::load(STAMPS_DIRECTORY).unwrap_or_else(|error| {
+            eprintln!(
+                "Failed to load stamps from {:?}: {}",
+                STAMPS_DIRECTORY, error
+            );
+            StampLibrary::empty()
+        });
+
+        stamps
+    }
+
+    fn import_image(&mut self, path: &std::path::Path) {
+        let colors = if std::path::Path::new(IMPORT_COLORS_FILE).exists() {
+            match ColorTable::load(IMPORT_COLORS_FILE) {
+                Ok(colors) => colors,
+                Err(error) => {
+                    eprintln!("Failed to load {:?}: {}", IMPORT_COLORS_FILE, error);
+                    return;
+                }
+            }
+        } else {
+            ColorTable::default()
+        };
+
+        match image::load_pattern(path, &colors) {
+            Ok(pattern) => {
+                self.clipboard = Some(pattern);
+                self.tool = Tool::Paste;
+            }
+            Err(error) => eprintln!("Failed to import {:?}: {}", path, error),
+        }
+    }
+
+    fn export_image(&self) {
+        let Some((from, to)) = self.selection.or_else(|| self.world_bounds()) else {
+            return;
+        };
+
+        let path = Self::export_path("world").with_extension("png");
+        let result = std::fs::create_dir_all(EXPORTS_DIRECTORY)
+            .and_then(|_| image::export_png(&self.world, from, to, &path));
+        match result {
+            Ok(()) => println!("Exported world to {:?}", path),
+            Err(error) => eprintln!("Failed to export {:?}: {}", path, error),
+        }
+    }
+
+    fn world_bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        self.world.bounds().unwrap_or_else(|error| {
+            eprintln!("Failed to read {:?}: {}", REGIONS_DIRECTORY, error);
+            None
+        })
+    }
+
+    fn draw_frame(&self, renderer: &Renderer, frame: &mut Frame, visible: &BoundingBox) {
+        let mut render_pass = renderer.create_default_render_pass(frame);
+        self.render_pipeline.prepare(&mut render_pass);
+        self.world_renderer.draw(&mut render_pass, visible);
+
+        self.ui_pipeline.prepare(&mut render_pass);
+        self.palette.draw(&mut render_pass);
+    }
+
+    fn save_screenshot(screenshot: &Screenshot) {
+        let path = Self::export_path("screenshot").with_extension("png");
+        let result = std::fs::create_dir_all(EXPORTS_DIRECTORY).and_then(|_| {
+            image::write_png(
+                &path,
+                screenshot.width,
+                screenshot.height,
+                &screenshot.pixels,
+            )
+        });
+        match result {
+            Ok(()) => println!("Saved screenshot to {:?}", path),
+            Err(error) => eprintln!("Failed to save {:?}: {}", path, error),
+        }
+    }
+
+    fn export_path(name: &str) -> std::path::PathBuf {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+        let directory = std::path::Path::new(EXPORTS_DIRECTORY);
+        let taken = |path: &std::path::Path| {
+            path.exists()
+                || ["png", "gif"]
+                    .into_iter()
+                    .any(|extension| path.with_extension(extension).exists())
+        };
+
+        let mut path = directory.join(format!("{name}_{timestamp}"));
+        let mut counter = 1;
+        while taken(&path) {
+            path = directory.join(format!("{name}_{timestamp}_{counter}"));
+            counter += 1;
+        }
+        path
+    }
+
+    fn toggle_timelapse(&mut self, rasterize_cells: bool) {
+        if let Some((timelapse, _)) = self.timelapse.take() {
+            let directory = timelapse.directory().to_path_buf();
+            let gif_path = timelapse.gif_path().map(|path| path.to_path_buf());
+            match timelapse.finish() {
+                Ok(frames) => println!(
+                    "Saved {} time-lapse frames to {:?} and {:?}",
+                    frames, directory, gif_path
+                ),
+                Err(error) => eprintln!("Failed to finish {:?}: {}", gif_path, error),
+            }
+            return;
+        }
+
+        let source = if rasterize_cells {
+            match self.selection.or_else(|| self.world_bounds()) {
+                Some((from, to)) => FrameSource::Cells(from, to),
+                None => return,
+            }
+        } else {
+            FrameSource::Renderer
+        };
+
+        let path = Self::export_path("timelapse");
+        match Timelapse::new(&path, TIMELAPSE_INTERVAL, Some(path.with_extension("gif"))) {
+            Ok(timelapse) => {
+                println!(
+                    "Recording a time-lapse frame every {TIMELAPSE_INTERVAL} ticks to {:?}",
+                    path
+                );
+                self.timelapse = Some((timelapse, source));
+            }
+            Err(error) => eprintln!("Failed to create {:?}: {}", path, error),
+        }
+    }
+
+    fn add_timelapse_frame(&mut self, width: u32, height: u32, pixels: &[u8]) {
+        let Some((timelapse, _)) = &mut self.timelapse else {
+            return;
+        };
+
+        if let Err(error) = timelapse.add_frame(width, height, pixels) {
+            eprintln!("Failed to add a time-lapse frame: {}", error);
+            self.toggle_timelapse(false);
+        }
+    }
+
+    fn active_pattern(&self) -> Option<&Pattern> {
+        match self.tool {
+            Tool::Paste => self.clipboard.as_ref(),
+            Tool::Stamp => self.stamps.selected().map(|stamp| &stamp.pattern),
+            _ => None,
+        }
+    }
+
+    fn active_pattern_mut(&mut self) -> Option<&mut Pattern> {
+        match self.tool {
+            Tool::Paste => self.clipboard.as_mut(),
+            Tool::Stamp => self.stamps.selected_mut().map(|stamp| &mut stamp.pattern),
+            _ => None,
+        }
+    }
+
+    fn status_title(&self) -> String {
+        if self.recovery_pending {
+            return format!("{WINDOW_TITLE} | Restore the autosave from the last session? (Y/N)");
+        }
+
+        let title = if self.tool == Tool::Brush {
+            format!(
+                "{WINDOW_TITLE} | Brush radius {} (Ctrl+scroll to resize)",
+                self.brush.radius
+            )
+        } else if self.tool != Tool::Stamp {
+            WINDOW_TITLE.to_string()
+        } else if self.stamps.stamps().is_empty() {
+            format!("{WINDOW_TITLE} | No stamps in {:?}", STAMPS_DIRECTORY)
+        } else {
+            let names: Vec<_> = self
+                .stamps
+                .stamps()
+                .iter()
+                .enumerate()
+                .map(|(index, stamp)| {
+                    if index == self.stamps.selected_index() {
+                        format!("[{}]", stamp.name)
+                    } else {
+                        stamp.name.clone()
+                    }
+                })
+                .collect();
+            format!(
+                "{WINDOW_TITLE} | Stamp {}/{}: {}",
+                self.stamps.selected_index() + 1,
+                self.stamps.stamps().len(),
+                names.join(" ")
+            )
+        };
+
+        if self.recording.is_some() {
+            format!("{title} | Recording")
+        } else {
+            title
+        }
+    }
+
+    fn paste_origin(pattern: &Pattern, cursor_cell: (i32, i32)) -> (i32, i32) {
+        (
+            cursor_cell.0 - pattern.width() as i32 / 2,
+            cursor_cell.1 - pattern.height() as i32 / 2,
+        )
+    }
+
+    fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        self.renderer.borrow_mut().resize(new_size);
+
+        self.camera
+            .update_size(new_size.width as f32, new_size.height as f32);
+        self.render_pipeline.update_camera(&self.camera);
+
+        self.ui_camera
+            .update_size(new_size.width as f32, new_size.height as f32);
+        self.ui_pipeline.update_camera(&self.ui_camera);
+        self.palette.update_position(self.ui_camera.view_size().1);
+    }
+
+    fn pan_camera(&mut self, input: &WinitInputHelper) {
+        let (mut dx, mut dy) = (0.0, 0.0);
+        if !input.held_control() {
+            let pan_keys = [
+                (KeyCode::KeyW, KeyCode::ArrowUp, (0.0, 1.0)),
+                (KeyCode::KeyS, KeyCode::ArrowDown, (0.0, -1.0)),
+                (KeyCode::KeyA, KeyCode::ArrowLeft, (-1.0, 0.0)),
+                (KeyCode::KeyD, KeyCode::ArrowRight, (1.0, 0.0)),
+            ];
+            let distance = CAMERA_PAN_SPEED / self.camera.zoom()
+                * input
+                    .delta_time()
+                    .map_or(0.0, |delta_time| delta_time.as_secs_f32());
+            for (key, arrow_key, direction) in pan_keys {
+                if input.key_held(key) || input.key_held(arrow_key) {
+                    dx += direction.0 * distance;
+                    dy += direction.1 * distance;
+                }
+            }
+        }
+
+        if input.mouse_held(2) {
+            let (cursor_dx, cursor_dy) = input.cursor_diff();
+            dx -= cursor_dx / self.camera.pixels_per_unit();
+            dy += cursor_dy / self.camera.pixels_per_unit();
+        }
+
+        if dx != 0.0 || dy != 0.0 {
+            self.camera.position.x += dx;
+            self.camera.position.y += dy;
+            self.render_pipeline.update_camera(&self.camera);
+        }
+    }
+
+    fn handle_edit_shortcuts(&mut self, input: &WinitInputHelper) {
+        if input.held_control() {
+            if let Some((from, to)) = self.selection {
+                if input.key_pressed(KeyCode::KeyC) || input.key_pressed(KeyCode::KeyX) {
+                    self.clipboard = Some(Pattern::copy(&self.world, from, to));
+                }
+                if input.key_pressed(KeyCode::KeyX) {
+                    let mut grid = EditRecorder::new(&mut self.world, self.recording.as_mut());
+                    for (x, y) in raster::rectangle(from, to, true) {
+                        grid.set_cell(x, y, None);
+                    }
+                }
+            }
+
+            if input.key_pressed(KeyCode::KeyV) && self.clipboard.is_some() {
+                self.tool = Tool::Paste;
+            }
+            if input.key_pressed(KeyCode::KeyE) {
+                self.export_image();
+            }
+            if input.key_pressed(KeyCode::KeyS) {
+                self.save_world();
+            }
+            if input.key_pressed(KeyCode::KeyO) {
+                self.load_world(std::path::Path::new(SAVE_FILE));
+            }
+            if input.key_pressed(KeyCode::KeyR) {
+                self.toggle_recording();
+            }
+            if input.key_pressed(KeyCode::KeyP) {
+                self.start_replay();
+            }
+            if input.key_pressed(KeyCode::KeyT) {
+                self.toggle_timelapse(input.held_shift());
+            }
+            return;
+        }
+
+        if self.tool == Tool::Stamp {
+            if input.key_pressed(KeyCode::BracketRight) {
+                self.stamps.select_next();
+            }
+            if input.key_pressed(KeyCode::BracketLeft) {
+                self.stamps.select_previous();
+            }
+        }
+
+        if let Some(pattern) = self.active_pattern_mut() {
+            if input.key_pressed(KeyCode::KeyT) {
+                *pattern = pattern.rotate_clockwise();
+            }
+            if input.key_pressed(KeyCode::KeyH) {
+                *pattern = if input.held_shift() {
+                    pattern.flip_vertical()
+                } else {
+                    pattern.flip_horizontal()
+                };
+            }
+        }
+
+        if self.tool == Tool::Paste && input.key_pressed(KeyCode::Escape) {
+            self.tool = Tool::Select;
+        }
+    }
+
+    fn set_title(&mut self, title: String) {
+        if title != self.title {
+            self.window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    fn inspect(&mut self, input: &WinitInputHelper, cursor_in_world: bool) {
+        let Some((x, y)) = self.cursor_cell.filter(|_| cursor_in_world) else {
+            self.set_title(self.status_title());
+            return;
+        };
+
+        let title = match self.world.get_cell(x, y) {
+            Some(cell) => {
+                if input.mouse_pressed(0) {
+                    self.palette.selected = cell.cell_type;
+                }
+
+                format!(
+                    "{WINDOW_TITLE} | ({x}, {y}) {}, last moved on tick {} ({} ticks at rest)",
+                    cell.cell_type.name(),
+                    cell.last_update,
+                    self.world.tick().saturating_sub(cell.last_update)
+                )
+            }
+            None => format!("{WINDOW_TITLE} | ({x}, {y}) Empty"),
+        };
+        self.set_title(title);
+    }
+
+    fn finish_drag(&mut self, input: &WinitInputHelper) {
+        let Some((start, erase)) = self.shape_start else {
+            return;
+        };
+        if input.mouse_held(if erase { 1 } else { 0 }) {
+            return;
+        }
+
+        let end = self.cursor_cell.or(self.last_cursor_cell).unwrap_or(start);
+        self.shape_start = None;
+        match self.tool {
+            Tool::Line | Tool::Rectangle | Tool::Circle => {
+                let cell = (!erase).then_some(Cell {
+                    cell_type: self.palette.selected,
+                    last_update: self.world.tick(),
+                });
+                let mut grid = EditRecorder::new(&mut self.world, self.recording.as_mut());
+                for (x, y) in self.tool.shape_cells(start, end, self.fill_shapes) {
+                    grid.set_cell(x, y, cell);
+                }
+            }
+            Tool::Select => self.selection = Some((start, end)),
+            _ => {}
+        }
+    }
+
+    fn use_tool(&mut self, input: &WinitInputHelper, cursor_in_world: bool) {
+        let material = Some(Cell {
+            cell_type: self.palette.selected,
+            last_update: self.world.tick(),
+        });
+
+        match self.tool {
+            Tool::Brush => {
+                let paint = if input.mouse_held(0) {
+                    Some(material)
+                } else if input.mouse_held(1) {
+                    Some(None)
+                } else {
+                    None
+                };
+
+                match (paint, self.cursor_cell) {
+                    (Some(cell), Some(cursor_cell))
+                        if cursor_in_world || self.last_paint_cell.is_some() =>
+                    {
+                        let from = self.last_paint_cell.unwrap_or(cursor_cell);
+                        self.paint(from, cursor_cell, cell);
+                        self.last_paint_cell = Some(cursor_cell);
+                    }
+                    _ => self.last_paint_cell = None,
+                }
+            }
+            Tool::Fill => {
+                if let Some(cursor_cell) = self.cursor_cell {
+                    let mut grid = EditRecorder::new(&mut self.world, self.recording.as_mut());
+                    if input.mouse_pressed(0) {
+                        fill::flood_fill(&mut grid, cursor_cell, material);
+                    } else if input.mouse_pressed(1) {
+                        fill::flood_fill(&mut grid, cursor_cell, None);
+                    }
+                }
+            }
+            Tool::Line | Tool::Rectangle | Tool::Circle => {
+                if let (None, Some(cursor_cell), true) =
+                    (self.shape_start, self.cursor_cell, cursor_in_world)
+                {
+                    if input.mouse_pressed(0) {
+                        self.shape_start = Some((cursor_cell, false));
+                    } else if input.mouse_pressed(1) {
+                        self.shape_start = Some((cursor_cell, true));
+                    }
+                }
+            }
+            Tool::Select => {
+                if let (Some(cursor_cell), true) = (self.cursor_cell, cursor_in_world) {
+                    if input.mouse_pressed(0) {
+                        self.shape_start = Some((cursor_cell, false));
+                    }
+                }
+                if input.mouse_pressed(1) {
+                    self.selection = None;
+                }
+
+                if let (Some((start, _)), Some(cursor_cell)) = (self.shape_start, self.cursor_cell)
+                {
+                    self.selection = Some((start, cursor_cell));
+                }
+            }
+            Tool::Paste | Tool::Stamp => {
+                if !input.mouse_pressed(0) {
+                    return;
+                }
+
+                if let (Some(pattern), Some(cursor_cell)) =
+                    (self.active_pattern().cloned(), self.cursor_cell)
+                {
+                    let tick = self.world.tick();
+                    pattern.paste(
+                        &mut EditRecorder::new(&mut self.world, self.recording.as_mut()),
+                        Self::paste_origin(&pattern, cursor_cell),
+                        tick,
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Application for FallingSandApplication {
+    fn update(&mut self) {
+        let now = std::time::Instant::now();
+        let delta_time = now.duration_since(self.last_update).as_secs_f32();
+        if delta_time < 0.2 || self.recovery_pending {
+            return;
+        }
+
+        match &mut self.replay {
+            Some(replay) => {
+                if let Err(error) = replay.apply(&mut self.world) {
+                    eprintln!("Failed to replay {:?}: {}", RECORDING_FILE, error);
+                    self.replay = None;
+                } else if replay.is_finished() {
+                    println!("Finished replay on tick {}", self.world.tick());
+                    self.replay = None;
+                }
+            }
+            None => self.stream_world(),
+        }
+        if self.simulate_visible_only && self.recording.is_none() && self.replay.is_none() {
+            let (from, to) = self
+                .world_renderer
+                .visible_cells(&self.camera.visible_bbox());
+            self.world.step_within(from, to);
+        } else {
+            self.world.step();
+        }
+        match &self.timelapse {
+            Some((timelapse, FrameSource::Cells(from, to)))
+                if timelapse.is_due(self.world.tick()) =>
+            {
+                match image::rasterize_world(&self.world, *from, *to) {
+                    Ok((width, height, pixels)) => self.add_timelapse_frame(width, height, &pixels),
+                    Err(error) => eprintln!("Failed to draw a time-lapse frame: {}", error),
+                }
+            }
+            Some((timelapse, FrameSource::Renderer)) if timelapse.is_due(self.world.tick()) => {
+                self.timelapse_frame_requested = true;
+            }
+            _ => {}
+        }
+        if self
+            .hash_interval
+            .is_some_and(|interval| self.world.tick().is_multiple_of(interval))
+        {
+            self.log_hash();
+        }
+        self.autosave.update(&self.world);
+        self.last_update = now;
+    }
+
+    fn draw(&mut self) {
+        let overlay = self.overlay();
+        let visible = self.camera.visible_bbox();
+        self.world_renderer
+            .upload(&self.render_pipeline, &self.world, &overlay, &visible);
+        self.palette.upload_pixels();
+
+        let mut renderer = self.renderer.borrow_mut();
+        if let Some(mut frame) = renderer.begin_render() {
+            self.draw_frame(&renderer, &mut frame, &visible);
+
+            let capture_requested = self.screenshot_requested || self.timelapse_frame_requested;
+            let capture = if capture_requested {
+                renderer.capture(&mut frame)
+            } else {
+                None
+            };
+            renderer.finish_render(frame);
+
+            // A surface that cannot be copied from is captured by drawing the frame again to a
+            // texture of its own.
+            let capture = if capture_requested && capture.is_none() {
+                let mut frame = renderer.begin_offscreen_render();
+                self.draw_frame(&renderer, &mut frame, &visible);
+                let capture = renderer.capture(&mut frame);
+                renderer.finish_render(frame);
+                if capture.is_none() {
+                    eprintln!("Screenshots are not supported by this surface");
+                }
+                capture
+            } else {
+                capture
+            };
+
+            let screenshot = capture.and_then(|capture| renderer.read_capture(capture));
+            drop(renderer);
+            if let Some(screenshot) = screenshot {
+                if self.screenshot_requested {
+                    Self::save_screenshot(&screenshot);
+                }
+                if self.timelapse_frame_requested {
+                    self.add_timelapse_frame(
+                        screenshot.width,
+                        screenshot.height,
+                        &screenshot.pixels,
+                    );
+                }
+            }
+            self.screenshot_requested = false;
+            self.timelapse_frame_requested = false;
+        }
+    }
+
+    fn handle_input(&mut self, input: &WinitInputHelper) {
+        if let Some(scale_factor) = input.scale_factor_changed() {
+            self.camera.set_scale_factor(scale_factor as f32);
+            self.ui_camera.set_scale_factor(scale_factor as f32);
+            self.resize(self.window.inner_size());
+        }
+        if let Some(new_size) = input.window_resized() {
+            self.resize(new_size);
+        }
+
+        if input.key_pressed(KeyCode::F12) {
+            self.screenshot_requested = true;
+        }
+        self.pan_camera(input);
+
+        if self.recovery_pending {
+            self.handle_recovery(input);
+            self.set_title(self.status_title());
+            return;
+        }
+
+        if self.replay.is_some() {
+            if input.held_control() && input.key_pressed(KeyCode::KeyP) {
+                self.replay = None;
+                println!("Stopped replay on tick {}", self.world.tick());
+            }
+            self.set_title(format!(
+                "{WINDOW_TITLE} | Replaying tick {}",
+                self.world.tick()
+            ));
+            return;
+        }
+
+        let digit_keys = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+            KeyCode::Digit5,
+            KeyCode::Digit6,
+            KeyCode::Digit7,
+            KeyCode::Digit8,
+            KeyCode::Digit9,
+        ];
+        for (key, material) in digit_keys.into_iter().zip(CellType::ALL) {
+            if input.key_pressed(key) {
+                self.palette.selected = material;
+            }
+        }
+
+        let previous_tool = self.tool;
+        if !input.held_control() {
+            let tool_keys = [
+                (KeyCode::KeyB, Tool::Brush),
+                (KeyCode::KeyF, Tool::Fill),
+                (KeyCode::KeyL, Tool::Line),
+                (KeyCode::KeyR, Tool::Rectangle),
+                (KeyCode::KeyC, Tool::Circle),
+                (KeyCode::KeyM, Tool::Select),
+                (KeyCode::KeyP, Tool::Stamp),
+            ];
+            for (key, tool) in tool_keys {
+                if input.key_pressed(key) {
+                    self.tool = tool;
+                }
+            }
+        }
+        if let Some(path) = input.dropped_file() {
+            self.import_image(&path);
+        }
+        if self.tool == Tool::Stamp && previous_tool != Tool::Stamp {
+            self.stamps = Self::load_stamps();
+        }
+        self.handle_edit_shortcuts(input);
+
+        if self.tool != previous_tool {
+            self.shape_start = None;
+        }
+
+        if input.key_pressed(KeyCode::Tab) {
+            match self.tool {
+                Tool::Brush => self.brush.shape = self.brush.shape.next(),
+                _ => self.fill_shapes = !self.fill_shapes,
+            }
+        }
+
+        if input.key_pressed(KeyCode::KeyZ) {
+            // Switching modes rounds the zoom, so keep the cell under the cursor in place.
+            let anchor = input.cursor().map(|(x, y)| {
+                let window_pos = (x, y).into();
+                (self.camera.window_pos_to_world_pos(window_pos), window_pos)
+            });
+            self.camera.set_pixel_perfect(!self.camera.pixel_perfect());
+            if let Some((world_pos, window_pos)) = anchor {
+                self.camera.anchor(world_pos, window_pos);
+            }
+            self.render_pipeline.update_camera(&self.camera);
+        }
+
+        let (_, scroll) = input.scroll_diff();
+        if scroll != 0.0 && input.held_control() {
+            self.brush.resize(scroll.signum() as i32);
+        } else if scroll != 0.0 {
+            if let Some((x, y)) = input.cursor() {
+                self.camera.zoom_towards((x, y).into(), scroll.signum());
+                self.render_pipeline.update_camera(&self.camera);
+            }
+        }
+
+        let world_pos = input
+            .cursor()
+            .map(|(x, y)| self.camera.window_pos_to_world_pos((x, y).into()));
+        self.cursor_cell =
+            world_pos.map(|world_pos| self.world_renderer.world_pos_to_cell(world_pos));
+        if self.cursor_cell.is_some() {
+            self.last_cursor_cell = self.cursor_cell;
+        }
+        self.finish_drag(input);
+        let cursor_in_world = self
+            .cursor_cell
+            .is_some_and(|(x, y)| self.world.in_bounds(x, y));
+
+        let palette_material = input.cursor().and_then(|(x, y)| {
+            self.palette
+                .material_at(self.ui_camera.window_pos_to_world_pos((x, y).into()))
+        });
+        if let Some(material) = palette_material {
+            if input.mouse_pressed(0) {
+                self.palette.selected = material;
+            }
+            self.last_paint_cell = None;
+            return;
+        }
+
+        self.inspecting = input.held_alt();
+        if self.inspecting {
+            self.inspect(input, cursor_in_world);
+            return;
+        }
+        self.set_title(self.status_title());
+
+        self.use_tool(input, cursor_in_world);
+    }
+
+    fn exit(&mut self) {
+        if let Err(error) = self.autosave.end_session() {
+            eprintln!("Failed to remove {:?}: {}", SESSION_LOCK_FILE, error);
+        }
+    }
+}
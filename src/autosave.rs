@@ -0,0 +1,74 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{save, world::World};
+
+pub struct Autosave {
+    path: PathBuf,
+    session_lock: PathBuf,
+    interval: Duration,
+    last_save: Instant,
+    worker: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl Autosave {
+    pub fn new(path: impl AsRef<Path>, session_lock: impl AsRef<Path>, interval: Duration) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            session_lock: session_lock.as_ref().to_path_buf(),
+            interval,
+            last_save: Instant::now(),
+            worker: None,
+        }
+    }
+
+    pub fn begin_session(&self) -> io::Result<bool> {
+        let crashed = self.session_lock.exists() && self.path.exists();
+        fs::write(&self.session_lock, std::process::id().to_string())?;
+        Ok(crashed)
+    }
+
+    pub fn end_session(&mut self) -> io::Result<()> {
+        self.wait();
+        fs::remove_file(&self.session_lock)
+    }
+
+    pub fn update(&mut self, world: &World) {
+        if self
+            .worker
+            .as_ref()
+            .is_some_and(|worker| worker.is_finished())
+        {
+            self.wait();
+        }
+
+        if self.worker.is_some() || self.last_save.elapsed() < self.interval {
+            return;
+        }
+
+        let snapshot = world.snapshot();
+        let path = self.path.clone();
+        self.worker = Some(std::thread::spawn(move || {
+            let temporary_path = path.with_extension("tmp");
+            save::save_world(&snapshot, &temporary_path)?;
+            fs::rename(temporary_path, path)
+        }));
+        self.last_save = Instant::now();
+    }
+
+    fn wait(&mut self) {
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+
+        match worker.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => eprintln!("Failed to autosave to {:?}: {}", self.path, error),
+            Err(_) => eprintln!("Autosave to {:?} panicked", self.path),
+        }
+    }
+}
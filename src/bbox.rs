@@ -10,4 +10,11 @@ impl BoundingBox {
             && position.y >= self.min.y
             && position.y <= self.max.y
     }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
 }
@@ -0,0 +1,72 @@
+use rand::Rng;
+
+use crate::raster;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushShape {
+    Circle,
+    Square,
+    Spray,
+}
+
+impl BrushShape {
+    pub fn next(self) -> Self {
+        match self {
+            BrushShape::Circle => BrushShape::Square,
+            BrushShape::Square => BrushShape::Spray,
+            BrushShape::Spray => BrushShape::Circle,
+        }
+    }
+}
+
+pub struct Brush {
+    pub shape: BrushShape,
+    pub radius: i32,
+}
+
+impl Brush {
+    pub const MAX_RADIUS: i32 = 32;
+    const SPRAY_DENSITY: f64 = 0.05;
+
+    pub fn new() -> Self {
+        Self {
+            shape: BrushShape::Circle,
+            radius: 2,
+        }
+    }
+
+    pub fn resize(&mut self, delta: i32) {
+        self.radius = (self.radius + delta).clamp(0, Self::MAX_RADIUS);
+    }
+
+    pub fn footprint(&self, center: (i32, i32), filled: bool) -> Vec<(i32, i32)> {
+        match self.shape {
+            BrushShape::Square => raster::rectangle(
+                (center.0 - self.radius, center.1 - self.radius),
+                (center.0 + self.radius, center.1 + self.radius),
+                filled,
+            ),
+            BrushShape::Circle | BrushShape::Spray => raster::circle(center, self.radius, filled),
+        }
+    }
+
+    pub fn cells(&self, center: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut cells = self.footprint(center, true);
+        if self.shape == BrushShape::Spray {
+            let mut rng = rand::thread_rng();
+            cells.retain(|_| rng.gen_bool(Self::SPRAY_DENSITY));
+        }
+
+        cells
+    }
+
+    pub fn outline(&self, center: (i32, i32)) -> Vec<(i32, i32)> {
+        self.footprint(center, false)
+    }
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,10 +1,19 @@
 use cgmath::EuclideanSpace;
 
+use crate::bbox::BoundingBox;
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 16.0;
+const ZOOM_FACTOR: f32 = 1.25;
+
 pub struct Camera {
     pub position: cgmath::Point3<f32>,
     projection: cgmath::Matrix4<f32>,
     width: f32,
     height: f32,
+    zoom: f32,
+    scale_factor: f32,
+    pixel_perfect: bool,
 }
 
 impl Camera {
@@ -14,25 +23,124 @@ impl Camera {
             projection: cgmath::ortho(0.0, width, 0.0, height, -1.0, 1.0),
             width,
             height,
+            zoom: 1.0,
+            scale_factor: 1.0,
+            pixel_perfect: false,
         }
     }
 
     pub fn update_size(&mut self, width: f32, height: f32) {
-        self.projection = cgmath::ortho(0.0, width, 0.0, height, -1.0, 1.0);
         self.width = width;
         self.height = height;
+        self.update_projection();
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+        self.set_zoom(self.zoom);
+    }
+
+    pub fn view_size(&self) -> (f32, f32) {
+        (
+            self.width / self.pixels_per_unit(),
+            self.height / self.pixels_per_unit(),
+        )
+    }
+
+    pub fn pixels_per_unit(&self) -> f32 {
+        self.zoom * self.scale_factor
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn pixel_perfect(&self) -> bool {
+        self.pixel_perfect
+    }
+
+    pub fn set_pixel_perfect(&mut self, pixel_perfect: bool) {
+        self.pixel_perfect = pixel_perfect;
+        self.set_zoom(self.zoom);
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = if self.pixel_perfect {
+            let pixels_per_unit = (zoom * self.scale_factor).round().clamp(
+                (MIN_ZOOM * self.scale_factor).ceil().max(1.0),
+                (MAX_ZOOM * self.scale_factor).floor(),
+            );
+            pixels_per_unit / self.scale_factor
+        } else {
+            zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+        };
+        self.update_projection();
+    }
+
+    pub fn zoom_towards(&mut self, window_pos: cgmath::Point2<f32>, steps: f32) {
+        let anchor = self.window_pos_to_world_pos(window_pos);
+        let zoom = if self.pixel_perfect {
+            self.zoom + steps / self.scale_factor
+        } else {
+            self.zoom * ZOOM_FACTOR.powf(steps)
+        };
+        self.set_zoom(zoom);
+
+        let moved = self.window_pos_to_world_pos(window_pos);
+        self.position.x += anchor.x - moved.x;
+        self.position.y += anchor.y - moved.y;
     }
 
     pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        let view = cgmath::Matrix4::from_translation(-self.position.to_vec());
+        let view = cgmath::Matrix4::from_translation(-self.view_position().to_vec());
         self.projection * view
     }
 
     pub fn window_pos_to_world_pos(&self, window_pos: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
-        let x = window_pos.x + self.position.x;
-        let y = (self.height - window_pos.y) + self.position.y;
+        let position = self.view_position();
+        let x = window_pos.x / self.pixels_per_unit() + position.x;
+        let y = (self.height - window_pos.y) / self.pixels_per_unit() + position.y;
+        cgmath::Point2::new(x, y)
+    }
+
+    pub fn world_pos_to_window_pos(&self, world_pos: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        let position = self.view_position();
+        let x = (world_pos.x - position.x) * self.pixels_per_unit();
+        let y = self.height - (world_pos.y - position.y) * self.pixels_per_unit();
         cgmath::Point2::new(x, y)
     }
+
+    /// Moves the camera so that `world_pos` is drawn at `window_pos`.
+    pub fn anchor(&mut self, world_pos: cgmath::Point2<f32>, window_pos: cgmath::Point2<f32>) {
+        let drawn_at = self.world_pos_to_window_pos(world_pos);
+        self.position.x += (drawn_at.x - window_pos.x) / self.pixels_per_unit();
+        self.position.y -= (drawn_at.y - window_pos.y) / self.pixels_per_unit();
+    }
+
+    pub fn visible_bbox(&self) -> BoundingBox {
+        BoundingBox {
+            min: self.window_pos_to_world_pos((0.0, self.height).into()),
+            max: self.window_pos_to_world_pos((self.width, 0.0).into()),
+        }
+    }
+
+    fn view_position(&self) -> cgmath::Point3<f32> {
+        if !self.pixel_perfect {
+            return self.position;
+        }
+
+        let pixels_per_unit = self.pixels_per_unit();
+        cgmath::Point3::new(
+            (self.position.x * pixels_per_unit).round() / pixels_per_unit,
+            (self.position.y * pixels_per_unit).round() / pixels_per_unit,
+            self.position.z,
+        )
+    }
+
+    fn update_projection(&mut self) {
+        let (width, height) = self.view_size();
+        self.projection = cgmath::ortho(0.0, width, 0.0, height, -1.0, 1.0);
+    }
 }
 
 #[repr(C)]
@@ -53,3 +161,75 @@ impl CameraUniform {
         self.view_proj = camera.build_view_projection_matrix().into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: cgmath::Point2<f32>, b: cgmath::Point2<f32>) {
+        assert!(
+            (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    fn camera(pixel_perfect: bool) -> Camera {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.set_scale_factor(1.5);
+        camera.set_pixel_perfect(pixel_perfect);
+        camera.position = (-123.4, 56.7, 0.0).into();
+        camera
+    }
+
+    #[test]
+    fn window_and_world_positions_round_trip() {
+        for pixel_perfect in [false, true] {
+            let mut camera = camera(pixel_perfect);
+            for zoom in [MIN_ZOOM, 1.0, 3.0, MAX_ZOOM] {
+                camera.set_zoom(zoom);
+                let window_pos = cgmath::Point2::new(321.0, 45.0);
+                let world_pos = camera.window_pos_to_world_pos(window_pos);
+                assert_near(camera.world_pos_to_window_pos(world_pos), window_pos);
+            }
+        }
+    }
+
+    #[test]
+    fn anchor_draws_the_world_position_at_the_window_position() {
+        for pixel_perfect in [false, true] {
+            let mut camera = camera(pixel_perfect);
+            camera.set_zoom(2.5);
+            let world_pos = cgmath::Point2::new(40.0, -75.0);
+            let window_pos = cgmath::Point2::new(100.0, 500.0);
+            camera.anchor(world_pos, window_pos);
+            let drawn_at = camera.world_pos_to_window_pos(world_pos);
+            let tolerance = if pixel_perfect { 1.0 + 1e-3 } else { 1e-3 };
+            assert!(
+                (drawn_at.x - window_pos.x).abs() < tolerance
+                    && (drawn_at.y - window_pos.y).abs() < tolerance,
+                "{drawn_at:?} != {window_pos:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        for pixel_perfect in [false, true] {
+            let mut camera = camera(pixel_perfect);
+            let window_pos = cgmath::Point2::new(600.0, 150.0);
+            for steps in [1.0, 1.0, -1.0, -1.0, -1.0] {
+                let anchor = camera.window_pos_to_world_pos(window_pos);
+                camera.zoom_towards(window_pos, steps);
+                // Pixel-perfect mode snaps the view to whole pixels.
+                let tolerance = if pixel_perfect { 1.0 + 1e-3 } else { 1e-3 };
+                let drift = camera.world_pos_to_window_pos(anchor);
+                assert!(
+                    (drift.x - window_pos.x).abs() < tolerance
+                        && (drift.y - window_pos.y).abs() < tolerance,
+                    "{drift:?} != {window_pos:?} at zoom {}",
+                    camera.zoom()
+                );
+            }
+        }
+    }
+}
@@ -1,25 +1,208 @@
-use crate::chunk::{Chunk, CHUNK_HEIGHT};
-
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Cell {
     pub cell_type: CellType,
     pub last_update: usize,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CellType {
     Sand,
+    Water,
+    Stone,
+}
+
+impl CellType {
+    pub const ALL: [CellType; 3] = [CellType::Sand, CellType::Water, CellType::Stone];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CellType::Sand => "Sand",
+            CellType::Water => "Water",
+            CellType::Stone => "Stone",
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            CellType::Sand => 'S',
+            CellType::Water => 'W',
+            CellType::Stone => '#',
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<CellType> {
+        CellType::ALL
+            .into_iter()
+            .find(|cell_type| cell_type.symbol() == symbol)
+    }
+
+    pub fn id(self) -> u8 {
+        match self {
+            CellType::Sand => 0,
+            CellType::Water => 1,
+            CellType::Stone => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<CellType> {
+        CellType::ALL
+            .into_iter()
+            .find(|cell_type| cell_type.id() == id)
+    }
+
+    pub fn color(self) -> [u8; 4] {
+        match self {
+            CellType::Sand => [255, 0, 0, 255],
+            CellType::Water => [0, 0, 255, 255],
+            CellType::Stone => [128, 128, 128, 255],
+        }
+    }
+
+    fn density(self) -> u8 {
+        match self {
+            CellType::Sand => 2,
+            CellType::Water => 1,
+            CellType::Stone => u8::MAX,
+        }
+    }
+}
+
+pub const EMPTY_COLOR: [u8; 4] = [0, 0, 0, 0];
+
+pub trait CellGrid {
+    fn in_bounds(&self, x: i32, y: i32) -> bool;
+    fn get_cell(&self, x: i32, y: i32) -> Option<Cell>;
+    fn set_cell(&mut self, x: i32, y: i32, cell: Option<Cell>);
+
+    fn update_cell(&mut self, x: i32, y: i32, update_counter: usize, seed: u64)
+    where
+        Self: Sized,
+    {
+        if let Some(cell) = self.get_cell(x, y) {
+            if cell.last_update == update_counter {
+                return;
+            }
+
+            let new_position = cell.move_cell(self, x, y, update_counter, seed);
+            if new_position != (x, y) {
+                self.set_cell(
+                    new_position.0,
+                    new_position.1,
+                    Some(Cell {
+                        last_update: update_counter,
+                        ..cell
+                    }),
+                );
+            }
+        }
+    }
 }
 
 impl Cell {
-    pub fn move_cell(&self, chunk: &mut Chunk, x: usize, y: usize) -> (usize, usize) {
-        if y < CHUNK_HEIGHT - 1 {
-            if chunk.cells[x][y + 1].is_none() {
-                chunk.cells[x][y + 1] = chunk.cells[x][y].take();
-                return (x, y + 1);
+    pub fn move_cell(
+        &self,
+        grid: &mut impl CellGrid,
+        x: i32,
+        y: i32,
+        update_counter: usize,
+        seed: u64,
+    ) -> (i32, i32) {
+        let direction = if random_bit(seed, update_counter, x, y) {
+            1
+        } else {
+            -1
+        };
+
+        let moves: &[(i32, i32)] = match self.cell_type {
+            CellType::Sand => &[(0, 1), (direction, 1), (-direction, 1)],
+            CellType::Water => &[
+                (0, 1),
+                (direction, 1),
+                (-direction, 1),
+                (direction, 0),
+                (-direction, 0),
+            ],
+            CellType::Stone => &[],
+        };
+
+        for &(dx, dy) in moves {
+            let (new_x, new_y) = (x + dx, y + dy);
+            if self.can_displace(grid, new_x, new_y) {
+                let displaced = grid.get_cell(new_x, new_y);
+                grid.set_cell(new_x, new_y, Some(*self));
+                grid.set_cell(x, y, displaced);
+                return (new_x, new_y);
             }
         }
 
         (x, y)
     }
+
+    fn can_displace(&self, grid: &impl CellGrid, x: i32, y: i32) -> bool {
+        if !grid.in_bounds(x, y) {
+            return false;
+        }
+
+        match grid.get_cell(x, y) {
+            Some(other) => other.cell_type.density() < self.cell_type.density(),
+            None => true,
+        }
+    }
+}
+
+fn random_bit(seed: u64, update_counter: usize, x: i32, y: i32) -> bool {
+    let mut hash = seed
+        ^ (update_counter as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (x as u32 as u64) << 32
+        ^ y as u32 as u64;
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+    hash & 1 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+
+    fn run(world: &str, ticks: usize) -> String {
+        let (mut chunk, (width, height)) = Chunk::parse(world).unwrap();
+        chunk.run(ticks, 0);
+        chunk.to_text(width, height)
+    }
+
+    #[test]
+    fn sand_falls() {
+        assert_eq!(run("S\n.\n.\n", 1), ".\nS\n.\n");
+        assert_eq!(run("S\n.\n.\n", 2), ".\n.\nS\n");
+    }
+
+    #[test]
+    fn sand_slides_diagonally() {
+        assert_eq!(run("S.\n#.\n", 1), "..\n#S\n");
+        assert_eq!(run(".S\n.#\n", 1), "..\nS#\n");
+    }
+
+    #[test]
+    fn sand_rests_on_the_grid_edge() {
+        assert_eq!(run("..\nSS\n", 5), "..\nSS\n");
+    }
+
+    #[test]
+    fn water_spreads() {
+        assert_eq!(run("W..\nW..\n", 1), "...\nWW.\n");
+    }
+
+    #[test]
+    fn sand_sinks_through_water() {
+        assert_eq!(run("S\nW\n", 1), "W\nS\n");
+        assert_eq!(run("S\nW\nW\n", 2), "W\nW\nS\n");
+    }
+
+    #[test]
+    fn stone_stays_put() {
+        assert_eq!(run("#.\n..\n", 3), "#.\n..\n");
+    }
 }
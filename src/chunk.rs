@@ -1,56 +1,117 @@
-use crate::cell::{Cell, CellType};
+use std::io;
+
+use crate::{
+    cell::{Cell, CellGrid, CellType, EMPTY_COLOR},
+    pattern::Pattern,
+};
 
 pub const CHUNK_WIDTH: usize = 64;
 pub const CHUNK_HEIGHT: usize = 64;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Chunk {
     pub cells: [[Option<Cell>; CHUNK_HEIGHT]; CHUNK_WIDTH],
 }
 
 impl Chunk {
     pub fn new() -> Self {
-        let mut cells = [[None; CHUNK_HEIGHT]; CHUNK_WIDTH];
-        cells[CHUNK_WIDTH / 2][CHUNK_HEIGHT / 2] = Some(Cell {
-            cell_type: CellType::Sand,
-            last_update: 0,
-        });
-
-        Self { cells }
+        Self {
+            cells: [[None; CHUNK_HEIGHT]; CHUNK_WIDTH],
+        }
     }
 
-    pub fn update(&mut self, update_counter: usize) {
+    /// Parses a text grid into the top-left corner of a chunk and returns the grid's size.
+    /// Every cell outside the grid is filled with stone, so the grid's edges act as walls.
+    pub fn parse(text: &str) -> io::Result<(Self, (usize, usize))> {
+        let pattern = Pattern::parse(text)?;
+        let (width, height) = (pattern.width(), pattern.height());
+        if width > CHUNK_WIDTH || height > CHUNK_HEIGHT {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "A {width}x{height} world does not fit in a {CHUNK_WIDTH}x{CHUNK_HEIGHT} chunk"
+                ),
+            ));
+        }
+
+        let mut chunk = Self::new();
         for x in 0..CHUNK_WIDTH {
             for y in 0..CHUNK_HEIGHT {
-                if let Some(cell) = self.cells[x][y] {
-                    if cell.last_update == update_counter {
-                        continue;
-                    }
-
-                    let new_position = cell.move_cell(self, x, y);
-                    if new_position != (x, y) {
-                        self.cells[new_position.0][new_position.1]
-                            .as_mut()
-                            .unwrap()
-                            .last_update = update_counter;
-                    }
+                if x >= width || y >= height {
+                    chunk.cells[x][y] = Some(Cell {
+                        cell_type: CellType::Stone,
+                        last_update: 0,
+                    });
                 }
             }
         }
+        pattern.paste(&mut chunk, (0, 0), 0);
+
+        Ok((chunk, (width, height)))
+    }
+
+    pub fn to_text(&self, width: usize, height: usize) -> String {
+        if width == 0 || height == 0 {
+            return String::new();
+        }
+
+        Pattern::copy(self, (0, 0), (width as i32 - 1, height as i32 - 1)).to_text()
+    }
+
+    pub fn run(&mut self, ticks: usize, seed: u64) {
+        for tick in 1..=ticks {
+            self.update(tick, seed);
+        }
+    }
+
+    pub fn update(&mut self, update_counter: usize, seed: u64) {
+        for x in 0..CHUNK_WIDTH as i32 {
+            for y in 0..CHUNK_HEIGHT as i32 {
+                self.update_cell(x, y, update_counter, seed);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().flatten().all(Option::is_none)
     }
 
     pub fn draw(&self, texture: &mut [u8]) {
         for x in 0..CHUNK_WIDTH {
             for y in 0..CHUNK_HEIGHT {
-                let start = (x * 4 + y * CHUNK_WIDTH * 4) as usize;
+                let start = x * 4 + y * CHUNK_WIDTH * 4;
                 if let Some(cell) = self.cells[x][y] {
-                    let color = match cell.cell_type {
-                        CellType::Sand => [255, 0, 0, 255],
-                    };
-                    texture[start..start + 4].copy_from_slice(&color);
+                    texture[start..start + 4].copy_from_slice(&cell.cell_type.color());
                 } else {
-                    texture[start..start + 4].copy_from_slice(&[0, 0, 0, 0]);
+                    texture[start..start + 4].copy_from_slice(&EMPTY_COLOR);
                 }
             }
         }
     }
 }
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CellGrid for Chunk {
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < CHUNK_WIDTH as i32 && y < CHUNK_HEIGHT as i32
+    }
+
+    fn get_cell(&self, x: i32, y: i32) -> Option<Cell> {
+        if self.in_bounds(x, y) {
+            self.cells[x as usize][y as usize]
+        } else {
+            None
+        }
+    }
+
+    fn set_cell(&mut self, x: i32, y: i32, cell: Option<Cell>) {
+        if self.in_bounds(x, y) {
+            self.cells[x as usize][y as usize] = cell;
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::cell::{Cell, CellGrid};
+
+pub fn flood_fill(grid: &mut impl CellGrid, start: (i32, i32), cell: Option<Cell>) -> usize {
+    if !grid.in_bounds(start.0, start.1) {
+        return 0;
+    }
+
+    let target = grid.get_cell(start.0, start.1).map(|cell| cell.cell_type);
+    if target == cell.map(|cell| cell.cell_type) {
+        return 0;
+    }
+
+    let mut filled = 0;
+    let mut stack = vec![start];
+    while let Some((x, y)) = stack.pop() {
+        if !grid.in_bounds(x, y) || grid.get_cell(x, y).map(|cell| cell.cell_type) != target {
+            continue;
+        }
+
+        grid.set_cell(x, y, cell);
+        filled += 1;
+
+        stack.extend([(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]);
+    }
+
+    filled
+}
@@ -0,0 +1,81 @@
+use std::io;
+
+use crate::{
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    save,
+    world::World,
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub struct StateHasher {
+    hash: u64,
+}
+
+impl StateHasher {
+    pub fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn write_chunk(&mut self, chunk: &Chunk) {
+        for x in 0..CHUNK_WIDTH {
+            for y in 0..CHUNK_HEIGHT {
+                let cell = chunk.cells[x][y];
+                self.write(&[save::cell_tag(&cell)]);
+                if let Some(cell) = cell {
+                    self.write(&(cell.last_update as u64).to_le_bytes());
+                }
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn world_hash(world: &World) -> io::Result<u64> {
+    let mut hasher = StateHasher::new();
+    hasher.write(&(world.tick() as u64).to_le_bytes());
+    hasher.write(&world.seed().to_le_bytes());
+
+    world.visit_chunks(|(chunk_x, chunk_y), chunk| {
+        if !chunk.is_empty() {
+            hasher.write(&chunk_x.to_le_bytes());
+            hasher.write(&chunk_y.to_le_bytes());
+            hasher.write_chunk(chunk);
+        }
+        Ok(())
+    })?;
+
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_hash_is_stable() {
+        let (chunk, _) = Chunk::parse("S.W\n.#.\nWWS\n").unwrap();
+        let world = World::from_chunks([((-1, 3), chunk)], 120, 0x5eed);
+
+        assert_eq!(world_hash(&world).unwrap(), 0x58a4_c247_3e3e_103f);
+    }
+}
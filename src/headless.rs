@@ -0,0 +1,90 @@
+use std::{
+    fmt, io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{cell::CellType, hash, save};
+
+pub struct Summary {
+    pub ticks: usize,
+    pub final_tick: usize,
+    pub elapsed: Duration,
+    pub cell_counts: Vec<(CellType, usize)>,
+    pub empty_cells: usize,
+    pub hash: u64,
+    /// The `(tick, hash)` of the world every time the tick reached a multiple of the hash
+    /// interval.
+    pub hashes: Vec<(usize, u64)>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        writeln!(
+            f,
+            "Ran {} ticks in {:.2}s ({:.1} ticks/s)",
+            self.ticks,
+            seconds,
+            self.ticks as f64 / seconds.max(f64::EPSILON)
+        )?;
+        writeln!(f, "Final tick: {}", self.final_tick)?;
+        for (cell_type, count) in &self.cell_counts {
+            writeln!(f, "{}: {}", cell_type.name(), count)?;
+        }
+        writeln!(f, "Empty: {}", self.empty_cells)?;
+        write!(f, "Hash: {:016x}", self.hash)
+    }
+}
+
+pub fn simulate(
+    input: impl AsRef<Path>,
+    ticks: usize,
+    output: impl AsRef<Path>,
+    hash_interval: Option<usize>,
+) -> io::Result<Summary> {
+    if hash_interval == Some(0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The hash interval must be at least 1",
+        ));
+    }
+
+    let mut world = save::load_world(input)?;
+
+    let mut hashes = Vec::new();
+    let start = Instant::now();
+    for _ in 0..ticks {
+        world.step();
+        if hash_interval.is_some_and(|interval| world.tick().is_multiple_of(interval)) {
+            hashes.push((world.tick(), hash::world_hash(&world)?));
+        }
+    }
+    let elapsed = start.elapsed();
+
+    save::save_world(&world, output)?;
+
+    let mut cell_counts: Vec<_> = CellType::ALL
+        .iter()
+        .map(|&cell_type| (cell_type, 0))
+        .collect();
+    let mut empty_cells = 0;
+    for (_, chunk) in world.loaded_chunks() {
+        for cell in chunk.cells.iter().flatten() {
+            match cell {
+                Some(cell) => cell_counts[cell.cell_type.id() as usize].1 += 1,
+                None => empty_cells += 1,
+            }
+        }
+    }
+
+    Ok(Summary {
+        ticks,
+        final_tick: world.tick(),
+        elapsed,
+        cell_counts,
+        empty_cells,
+        hash: hash::world_hash(&world)?,
+        hashes,
+    })
+}
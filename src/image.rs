@@ -0,0 +1,238 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    cell::{CellGrid, CellType, EMPTY_COLOR},
+    chunk::{CHUNK_HEIGHT, CHUNK_WIDTH},
+    pattern::{Pattern, EMPTY_SYMBOL},
+    world::World,
+};
+
+const MAX_IMAGE_PIXELS: u64 = 1 << 28;
+
+pub struct ColorTable {
+    entries: Vec<([u8; 3], Option<CellType>)>,
+}
+
+impl ColorTable {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let invalid_line = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Expected `RRGGBB <symbol>` but found {line:?}"),
+            )
+        };
+
+        let mut entries = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (hex, symbol) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| invalid_line(line))?;
+            let color = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6 && hex.chars().all(|digit| digit.is_ascii_hexdigit()))
+                .ok_or_else(|| invalid_line(line))?;
+
+            let mut symbols = symbol.trim().chars();
+            let cell_type = match (symbols.next(), symbols.next()) {
+                (Some(EMPTY_SYMBOL), None) => None,
+                (Some(symbol), None) => {
+                    Some(CellType::from_symbol(symbol).ok_or_else(|| invalid_line(line))?)
+                }
+                _ => return Err(invalid_line(line)),
+            };
+
+            let [_, r, g, b] = color.to_be_bytes();
+            entries.push(([r, g, b], cell_type));
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn nearest(&self, color: [u8; 3]) -> Option<CellType> {
+        self.entries
+            .iter()
+            .min_by_key(|(entry, _)| {
+                entry
+                    .iter()
+                    .zip(color)
+                    .map(|(a, b)| (*a as i32 - b as i32).pow(2))
+                    .sum::<i32>()
+            })
+            .and_then(|(_, cell_type)| *cell_type)
+    }
+}
+
+impl Default for ColorTable {
+    fn default() -> Self {
+        let mut entries = vec![([0, 0, 0], None)];
+        for cell_type in CellType::ALL {
+            let [r, g, b, _] = cell_type.color();
+            entries.push(([r, g, b], Some(cell_type)));
+        }
+
+        Self { entries }
+    }
+}
+
+pub fn load_pattern(path: impl AsRef<Path>, colors: &ColorTable) -> io::Result<Pattern> {
+    let mut decoder = png::Decoder::new(fs::File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let channels = info.color_type.samples();
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut pattern = Pattern::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let start = y * info.line_size + x * channels;
+            let pixel = &buffer[start..start + channels];
+            let (color, alpha) = match info.color_type {
+                png::ColorType::Grayscale => ([pixel[0]; 3], 255),
+                png::ColorType::GrayscaleAlpha => ([pixel[0]; 3], pixel[1]),
+                png::ColorType::Rgb => ([pixel[0], pixel[1], pixel[2]], 255),
+                png::ColorType::Rgba => ([pixel[0], pixel[1], pixel[2]], pixel[3]),
+                png::ColorType::Indexed => unreachable!("Palette is expanded by the decoder"),
+            };
+
+            if alpha >= 128 {
+                pattern.set(x, y, colors.nearest(color));
+            }
+        }
+    }
+
+    Ok(pattern)
+}
+
+pub fn export_png(
+    world: &World,
+    from: (i32, i32),
+    to: (i32, i32),
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let (width, height, pixels) = rasterize_world(world, from, to)?;
+    write_png(path, width, height, &pixels)
+}
+
+pub fn rasterize(grid: &impl CellGrid, from: (i32, i32), to: (i32, i32)) -> (u32, u32, Vec<u8>) {
+    let (min_x, min_y) = (from.0.min(to.0), from.1.min(to.1));
+    let width = (from.0 - to.0).unsigned_abs() + 1;
+    let height = (from.1 - to.1).unsigned_abs() + 1;
+
+    let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let color = match grid.get_cell(min_x + x, min_y + y) {
+                Some(cell) => cell.cell_type.color(),
+                None => EMPTY_COLOR,
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+
+    (width, height, pixels)
+}
+
+/// Like [`rasterize`], but also draws the chunks of a streamed world that are only stored in
+/// region files.
+pub fn rasterize_world(
+    world: &World,
+    from: (i32, i32),
+    to: (i32, i32),
+) -> io::Result<(u32, u32, Vec<u8>)> {
+    let (min_x, min_y) = (from.0.min(to.0), from.1.min(to.1));
+    let width = (from.0 - to.0).unsigned_abs() + 1;
+    let height = (from.1 - to.1).unsigned_abs() + 1;
+    if width as u64 * height as u64 > MAX_IMAGE_PIXELS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("A {width}x{height} image has more than {MAX_IMAGE_PIXELS} pixels"),
+        ));
+    }
+
+    let mut pixels = EMPTY_COLOR.repeat((width * height) as usize);
+    world.visit_chunks(|(chunk_x, chunk_y), chunk| {
+        for (x, column) in chunk.cells.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                let image_x = chunk_x as i64 * CHUNK_WIDTH as i64 + x as i64 - min_x as i64;
+                let image_y = chunk_y as i64 * CHUNK_HEIGHT as i64 + y as i64 - min_y as i64;
+                let Some(cell) = cell else {
+                    continue;
+                };
+                if (0..width as i64).contains(&image_x) && (0..height as i64).contains(&image_y) {
+                    let index = (image_y as usize * width as usize + image_x as usize) * 4;
+                    pixels[index..index + 4].copy_from_slice(&cell.cell_type.color());
+                }
+            }
+        }
+
+        Ok(())
+    })?;
+
+    Ok((width, height, pixels))
+}
+
+pub fn write_png(path: impl AsRef<Path>, width: u32, height: u32, pixels: &[u8]) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(io::BufWriter::new(fs::File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::ColorTable;
+    use crate::cell::CellType;
+
+    const COLORS: &str = "000000 .\nFF0000 S\n\n  0000ff W\n";
+
+    #[test]
+    fn color_table_parses() {
+        let colors = ColorTable::parse(COLORS).unwrap();
+        assert_eq!(
+            colors.entries,
+            [
+                ([0, 0, 0], None),
+                ([255, 0, 0], Some(CellType::Sand)),
+                ([0, 0, 255], Some(CellType::Water)),
+            ]
+        );
+    }
+
+    #[test]
+    fn color_table_rejects_invalid_lines() {
+        for line in [
+            "FF0000",
+            "FF00 S",
+            "FF00000 S",
+            "GG0000 S",
+            "+F0000 S",
+            "FF0000 SW",
+            "FF0000 ?",
+        ] {
+            let error = ColorTable::parse(line).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn nearest_color_wins() {
+        let colors = ColorTable::parse(COLORS).unwrap();
+        assert_eq!(colors.nearest([200, 40, 30]), Some(CellType::Sand));
+        assert_eq!(colors.nearest([20, 60, 180]), Some(CellType::Water));
+        assert_eq!(colors.nearest([30, 20, 40]), None);
+    }
+}
@@ -0,0 +1,47 @@
+//! A falling sand simulation over an unbounded world of chunks.
+//!
+//! The simulation, saves, recordings and image import and export work without any features.
+//! The `app` feature adds the interactive sandbox in [`app`] and offscreen rendering in
+//! [`offscreen`].
+
+pub mod brush;
+pub mod cell;
+pub mod chunk;
+pub mod fill;
+pub mod hash;
+pub mod headless;
+pub mod image;
+pub mod pattern;
+pub mod raster;
+pub mod region;
+pub mod replay;
+pub mod save;
+pub mod stamp;
+pub mod timelapse;
+pub mod world;
+
+#[cfg(feature = "app")]
+pub mod app;
+#[cfg(feature = "app")]
+pub mod offscreen;
+
+#[cfg(feature = "app")]
+mod autosave;
+#[cfg(feature = "app")]
+mod bbox;
+#[cfg(feature = "app")]
+mod camera;
+#[cfg(feature = "app")]
+mod palette;
+#[cfg(feature = "app")]
+mod quad;
+#[cfg(feature = "app")]
+mod render;
+#[cfg(feature = "app")]
+mod texture;
+#[cfg(feature = "app")]
+mod tool;
+#[cfg(feature = "app")]
+mod window;
+#[cfg(feature = "app")]
+mod world_renderer;
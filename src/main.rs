@@ -1,157 +1,186 @@
-use bbox::BoundingBox;
-use camera::Camera;
-use chunk::Chunk;
-use quad::Quad;
-use render::{drawable::Drawable, pipeline::RenderPipeline2D, renderer::Renderer};
-use std::{cell::RefCell, rc::Rc, sync::Arc};
-use window::{Application, WindowManager};
-use winit_input_helper::WinitInputHelper;
-
-mod bbox;
-mod camera;
-mod cell;
-mod chunk;
-mod quad;
-mod render;
-mod texture;
-mod window;
-
-#[repr(C)]
-#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct ModelUniform {
-    model: [[f32; 4]; 4],
-}
+use std::io;
+
+#[cfg(feature = "app")]
+use falling_sand::{app, offscreen};
+use falling_sand::{chunk::Chunk, headless};
+
+const SIMULATE_USAGE: &str = "simulate <world> <ticks> <output> [--hash-every <ticks>]";
+const ASCII_USAGE: &str = "ascii <world> <ticks>";
+#[cfg(feature = "app")]
+const RENDER_USAGE: &str = "render <world> <output> [--fallback-adapter]";
+#[cfg(feature = "app")]
+const TIMELAPSE_USAGE: &str =
+    "timelapse <world> <ticks> <interval> <directory> [--gif <file>] [--cpu] [--fallback-adapter]";
+#[cfg(feature = "app")]
+const FRAME_SIZE: (u32, u32) = (800, 600);
+const ASCII_WORLD_SEED: u64 = 0;
 
-impl ModelUniform {
-    pub fn new() -> Self {
-        use cgmath::SquareMatrix;
-        Self {
-            model: cgmath::Matrix4::identity().into(),
-        }
-    }
+fn main() {
+    #[cfg(feature = "app")]
+    env_logger::init();
 
-    pub fn update_model(&mut self, position: cgmath::Point3<f32>) {
-        use cgmath::EuclideanSpace;
-        self.model = cgmath::Matrix4::from_translation(position.to_vec()).into()
+    let args: Vec<String> = std::env::args().collect();
+    let (action, result) = match args.get(1).map(String::as_str) {
+        Some("simulate") => ("simulate", simulate(&args[2..])),
+        Some("ascii") => ("run the ASCII world", run_ascii_world(&args[2..])),
+        #[cfg(feature = "app")]
+        Some("render") => ("render", render(&args[2..])),
+        #[cfg(feature = "app")]
+        Some("timelapse") => ("record time-lapse", record_timelapse(&args[2..])),
+        #[cfg(feature = "app")]
+        _ => ("start the sandbox", run_app(&args[1..])),
+        #[cfg(not(feature = "app"))]
+        _ => (
+            "run",
+            Err(usage(&format!(
+                "{SIMULATE_USAGE} or {ASCII_USAGE} (the sandbox, render and timelapse \
+                 need the app feature)"
+            ))),
+        ),
+    };
+
+    if let Err(error) = result {
+        eprintln!("Failed to {action}: {error}");
+        std::process::exit(1);
     }
 }
 
-struct FallingSandApplication {
-    renderer: Rc<RefCell<Renderer>>,
-    render_pipeline: RenderPipeline2D,
-
-    last_update: std::time::Instant,
-    update_counter: usize,
-
-    camera: Camera,
-
-    chunk: Chunk,
-    chunk_bbox: BoundingBox,
-    chunk_quad: Quad,
-    chunk_pixels: Vec<u8>,
-}
-
-impl FallingSandApplication {
-    pub fn new(window: Arc<winit::window::Window>) -> Self {
-        let renderer = Rc::new(RefCell::new(pollster::block_on(Renderer::new(
-            window.clone(),
-        ))));
-
-        let chunk = Chunk::new();
-        let chunk_bbox = BoundingBox {
-            min: (128.0, 128.0).into(),
-            max: (256.0, 256.0).into(),
-        };
-
-        let chunk_quad = Quad::new(&renderer.borrow().device, (128, 128));
-        let mut chunk_pixels: Vec<u8> = Vec::with_capacity(64 * 64 * 4);
-        for _ in 0..64 * 64 {
-            chunk_pixels.extend_from_slice(&[0, 0, 0, 0]);
+fn simulate(args: &[String]) -> io::Result<()> {
+    let (input, ticks, output, hash_interval) = match args {
+        [input, ticks, output] => (input, ticks, output, None),
+        [input, ticks, output, flag, interval] if flag == "--hash-every" => {
+            (input, ticks, output, Some(parse_hash_interval(interval)?))
         }
+        _ => return Err(usage(SIMULATE_USAGE)),
+    };
 
-        let size = window.inner_size();
-        let camera = Camera::new(size.width as f32, size.height as f32);
-
-        let mut render_pipeline = RenderPipeline2D::new(renderer.clone());
-        render_pipeline.update_camera(&camera);
-        render_pipeline.update_model((128.0, 128.0, 0.0).into());
-
-        Self {
-            renderer,
-            render_pipeline,
-
-            last_update: std::time::Instant::now(),
-            update_counter: 0,
-
-            camera,
-
-            chunk,
-            chunk_bbox,
-            chunk_quad,
-            chunk_pixels,
-        }
+    let summary = headless::simulate(input, parse_count(ticks)?, output, hash_interval)?;
+    for (tick, hash) in &summary.hashes {
+        println!("Tick {tick} hash {hash:016x}");
     }
+    println!("{summary}");
+    Ok(())
 }
 
-impl Application for FallingSandApplication {
-    fn update(&mut self) {
-        let now = std::time::Instant::now();
-        let delta_time = now.duration_since(self.last_update).as_secs_f32();
-        if delta_time < 0.2 {
-            return;
-        }
+fn run_ascii_world(args: &[String]) -> io::Result<()> {
+    let [path, ticks] = args else {
+        return Err(usage(ASCII_USAGE));
+    };
 
-        self.chunk.update(self.update_counter);
-        self.last_update = now;
-        self.update_counter += 1;
-    }
+    let (mut chunk, (width, height)) = Chunk::parse(&std::fs::read_to_string(path)?)?;
+    chunk.run(parse_count(ticks)?, ASCII_WORLD_SEED);
+    print!("{}", chunk.to_text(width, height));
+    Ok(())
+}
 
-    fn draw(&mut self) {
-        self.chunk.draw(&mut self.chunk_pixels);
-        self.render_pipeline
-            .texture
-            .upload_pixels(&self.renderer.borrow().queue, &self.chunk_pixels);
-
-        let mut renderer = self.renderer.borrow_mut();
-        if let Some(mut frame) = renderer.begin_render() {
-            {
-                let mut render_pass = renderer.create_default_render_pass(&mut frame);
-                self.render_pipeline.prepare(&mut render_pass);
-                self.chunk_quad.draw(&mut render_pass);
-            }
+#[cfg(feature = "app")]
+fn render(args: &[String]) -> io::Result<()> {
+    let (path, output, force_fallback_adapter) = match args {
+        [path, output] => (path, output, false),
+        [path, output, flag] if flag == "--fallback-adapter" => (path, output, true),
+        _ => return Err(usage(RENDER_USAGE)),
+    };
+
+    let info = offscreen::render_world(path, output, FRAME_SIZE, force_fallback_adapter)?;
+    println!(
+        "Rendered a {}x{} frame of {:?} to {:?} on {} ({:?}, {:?})",
+        FRAME_SIZE.0, FRAME_SIZE.1, path, output, info.name, info.device_type, info.backend
+    );
+    Ok(())
+}
 
-            renderer.finish_render(frame);
+#[cfg(feature = "app")]
+fn record_timelapse(args: &[String]) -> io::Result<()> {
+    let [path, ticks, interval, directory, options @ ..] = args else {
+        return Err(usage(TIMELAPSE_USAGE));
+    };
+    let ticks: usize = ticks.parse().map_err(|_| usage(TIMELAPSE_USAGE))?;
+    let interval: usize = match interval.parse() {
+        Ok(interval) if interval > 0 => interval,
+        _ => return Err(usage(TIMELAPSE_USAGE)),
+    };
+
+    let mut timelapse = offscreen::TimelapseOptions {
+        ticks,
+        interval,
+        gif_path: None,
+        rasterize_cells: false,
+        size: FRAME_SIZE,
+        force_fallback_adapter: false,
+    };
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--gif" => {
+                let path = options.next().ok_or_else(|| usage(TIMELAPSE_USAGE))?;
+                timelapse.gif_path = Some(path.into());
+            }
+            "--cpu" => timelapse.rasterize_cells = true,
+            "--fallback-adapter" => timelapse.force_fallback_adapter = true,
+            _ => return Err(usage(TIMELAPSE_USAGE)),
         }
     }
 
-    fn handle_input(&mut self, input: &WinitInputHelper) {
-        if let Some(new_size) = input.window_resized() {
-            self.renderer.borrow_mut().resize(new_size);
-
-            self.camera
-                .update_size(new_size.width as f32, new_size.height as f32);
-            self.render_pipeline.update_camera(&self.camera);
-        }
-
-        if input.mouse_held(0) {
-            if let Some((x, y)) = input.cursor() {
-                let world_pos = self
-                    .camera
-                    .window_pos_to_world_pos((x as f32, y as f32).into());
+    let frames = offscreen::record_timelapse(path, directory, timelapse)?;
+    println!(
+        "Recorded {} frames of {:?} over {} ticks to {:?}",
+        frames, path, ticks, directory
+    );
+    Ok(())
+}
 
-                if self.chunk_bbox.contains(world_pos.into()) {
-                    println!("In chunk: {:?}", world_pos);
-                }
+#[cfg(feature = "app")]
+fn run_app(args: &[String]) -> io::Result<()> {
+    let mut options = app::Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hash-every" => {
+                let interval = args.next().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "--hash-every needs an interval",
+                    )
+                })?;
+                options.hash_interval = Some(parse_hash_interval(interval)?);
+            }
+            "--simulate-visible" => options.simulate_visible_only = true,
+            "--fallback-adapter" => options.force_fallback_adapter = true,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unknown option {arg:?}"),
+                ))
             }
         }
     }
+
+    app::run(options)
 }
 
-fn main() {
-    env_logger::init();
+fn parse_count(value: &str) -> io::Result<usize> {
+    value.parse().map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid tick count {value:?}: {error}"),
+        )
+    })
+}
 
-    let window_manager = WindowManager::new("Falling Sand", (800, 600));
-    let app = Box::new(FallingSandApplication::new(window_manager.window.clone()));
+fn parse_hash_interval(value: &str) -> io::Result<usize> {
+    match value.parse() {
+        Ok(0) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid --hash-every interval 0: it must be at least 1",
+        )),
+        Ok(interval) => Ok(interval),
+        Err(error) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid --hash-every interval {value:?}: {error}"),
+        )),
+    }
+}
 
-    window_manager.run(app);
+fn usage(usage: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("Usage: {usage}"))
 }
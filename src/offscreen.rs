@@ -0,0 +1,162 @@
+//! Rendering saved worlds without a window.
+
+use std::{
+    cell::RefCell,
+    io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{
+    bbox::BoundingBox,
+    camera::Camera,
+    image,
+    render::{
+        pipeline::RenderPipeline2D,
+        renderer::{RenderTarget, Renderer, Screenshot},
+    },
+    save,
+    timelapse::Timelapse,
+    world::World,
+    world_renderer::WorldRenderer,
+};
+
+pub(crate) struct OffscreenRenderer {
+    renderer: Rc<RefCell<Renderer>>,
+    render_pipeline: RenderPipeline2D,
+    world_renderer: WorldRenderer,
+    visible: BoundingBox,
+}
+
+impl OffscreenRenderer {
+    pub fn new(world: &World, size: (u32, u32), force_fallback_adapter: bool) -> io::Result<Self> {
+        let renderer = Rc::new(RefCell::new(pollster::block_on(Renderer::new(
+            RenderTarget::Offscreen {
+                width: size.0,
+                height: size.1,
+            },
+            force_fallback_adapter,
+        ))?));
+
+        let world_renderer = WorldRenderer::new();
+        let (center_x, center_y) = world.loaded_bounds().map_or((0.0, 0.0), |(from, to)| {
+            let bbox = WorldRenderer::cells_bbox(from, to);
+            (
+                (bbox.min.x + bbox.max.x) / 2.0,
+                (bbox.min.y + bbox.max.y) / 2.0,
+            )
+        });
+        let mut camera = Camera::new(size.0 as f32, size.1 as f32);
+        camera.position.x = center_x - size.0 as f32 / 2.0;
+        camera.position.y = center_y - size.1 as f32 / 2.0;
+
+        let mut render_pipeline = RenderPipeline2D::new(renderer.clone());
+        render_pipeline.update_camera(&camera);
+
+        Ok(Self {
+            renderer,
+            render_pipeline,
+            world_renderer,
+            visible: camera.visible_bbox(),
+        })
+    }
+
+    pub fn renderer(&self) -> &Rc<RefCell<Renderer>> {
+        &self.renderer
+    }
+
+    pub fn render(&mut self, world: &World) -> Screenshot {
+        self.world_renderer
+            .upload(&self.render_pipeline, world, &[], &self.visible);
+
+        let mut renderer = self.renderer.borrow_mut();
+        let mut frame = renderer
+            .begin_render()
+            .expect("Offscreen frames are always available");
+        {
+            let mut render_pass = renderer.create_default_render_pass(&mut frame);
+            self.render_pipeline.prepare(&mut render_pass);
+            self.world_renderer.draw(&mut render_pass, &self.visible);
+        }
+        let capture = renderer
+            .capture(&mut frame)
+            .expect("Offscreen frames can always be captured");
+        renderer.finish_render(frame);
+
+        renderer
+            .read_capture(capture)
+            .expect("Failed to read back the offscreen frame")
+    }
+}
+
+/// Renders the saved world at `input`, centred on its chunks, to a PNG at `output` and returns
+/// the adapter that drew it.
+///
+/// Fails if the world cannot be read or no graphics adapter, not even a fallback, is available.
+pub fn render_world(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    size: (u32, u32),
+    force_fallback_adapter: bool,
+) -> io::Result<wgpu::AdapterInfo> {
+    let world = save::load_world(input)?;
+    let mut offscreen = OffscreenRenderer::new(&world, size, force_fallback_adapter)?;
+    let screenshot = offscreen.render(&world);
+    image::write_png(
+        output,
+        screenshot.width,
+        screenshot.height,
+        &screenshot.pixels,
+    )?;
+
+    let info = offscreen.renderer().borrow().adapter_info();
+    Ok(info)
+}
+
+/// Options for [`record_timelapse`].
+pub struct TimelapseOptions {
+    pub ticks: usize,
+    /// Number of ticks between frames.
+    pub interval: usize,
+    /// Also encodes the frames into an animated GIF at this path.
+    pub gif_path: Option<PathBuf>,
+    /// Draws one pixel per cell on the CPU instead of rendering frames on the GPU.
+    pub rasterize_cells: bool,
+    pub size: (u32, u32),
+    pub force_fallback_adapter: bool,
+}
+
+/// Simulates the saved world at `input` and writes a PNG frame to `directory` every
+/// `options.interval` ticks. Returns the number of frames written.
+pub fn record_timelapse(
+    input: impl AsRef<Path>,
+    directory: impl AsRef<Path>,
+    options: TimelapseOptions,
+) -> io::Result<usize> {
+    let mut world = save::load_world(input)?;
+    let mut timelapse = Timelapse::new(directory, options.interval, options.gif_path)?;
+    let mut offscreen = (!options.rasterize_cells)
+        .then(|| OffscreenRenderer::new(&world, options.size, options.force_fallback_adapter))
+        .transpose()?;
+    let bounds = world.loaded_bounds().unwrap_or_default();
+
+    for _ in 0..options.ticks {
+        world.step();
+        if !timelapse.is_due(world.tick()) {
+            continue;
+        }
+
+        match &mut offscreen {
+            Some(offscreen) => {
+                let screenshot = offscreen.render(&world);
+                timelapse.add_frame(screenshot.width, screenshot.height, &screenshot.pixels)?;
+            }
+            None => {
+                let (width, height, pixels) = image::rasterize(&world, bounds.0, bounds.1);
+                timelapse.add_frame(width, height, &pixels)?;
+            }
+        }
+    }
+
+    timelapse.finish()
+}
@@ -0,0 +1,96 @@
+use crate::{
+    bbox::BoundingBox,
+    cell::CellType,
+    render::{pipeline::RenderPipeline2D, sprite::Sprite},
+};
+
+pub struct Palette {
+    pub selected: CellType,
+
+    sprite: Sprite,
+    bbox: BoundingBox,
+    pixels: Vec<u8>,
+}
+
+impl Palette {
+    const SWATCH_PIXELS: usize = 8;
+    const SWATCH_SIZE: usize = 32;
+    const MARGIN: f32 = 8.0;
+
+    pub fn new(pipeline: &RenderPipeline2D, window_height: f32) -> Self {
+        let texture_size = (
+            CellType::ALL.len() * Self::SWATCH_PIXELS,
+            Self::SWATCH_PIXELS,
+        );
+        let sprite = pipeline.create_sprite(
+            texture_size,
+            (CellType::ALL.len() * Self::SWATCH_SIZE, Self::SWATCH_SIZE),
+        );
+
+        let mut palette = Self {
+            selected: CellType::ALL[0],
+
+            sprite,
+            bbox: BoundingBox {
+                min: (0.0, 0.0).into(),
+                max: (0.0, 0.0).into(),
+            },
+            pixels: vec![0; texture_size.0 * texture_size.1 * 4],
+        };
+        palette.update_position(window_height);
+        palette
+    }
+
+    pub fn update_position(&mut self, window_height: f32) {
+        let min = cgmath::Point2::new(
+            Self::MARGIN,
+            window_height - Self::MARGIN - Self::SWATCH_SIZE as f32,
+        );
+        self.bbox = BoundingBox {
+            min,
+            max: (
+                min.x + (CellType::ALL.len() * Self::SWATCH_SIZE) as f32,
+                min.y + Self::SWATCH_SIZE as f32,
+            )
+                .into(),
+        };
+        self.sprite.update_model((min.x, min.y, 0.0).into());
+    }
+
+    pub fn material_at(&self, ui_pos: cgmath::Point2<f32>) -> Option<CellType> {
+        if !self.bbox.contains(ui_pos) {
+            return None;
+        }
+
+        let index = ((ui_pos.x - self.bbox.min.x) / Self::SWATCH_SIZE as f32) as usize;
+        CellType::ALL.get(index).copied()
+    }
+
+    pub fn upload_pixels(&mut self) {
+        let row_width = CellType::ALL.len() * Self::SWATCH_PIXELS;
+        for (index, material) in CellType::ALL.iter().enumerate() {
+            for x in 0..Self::SWATCH_PIXELS {
+                for y in 0..Self::SWATCH_PIXELS {
+                    let border = x == 0
+                        || y == 0
+                        || x == Self::SWATCH_PIXELS - 1
+                        || y == Self::SWATCH_PIXELS - 1;
+                    let color = match (border, *material == self.selected) {
+                        (true, true) => [255, 255, 255, 255],
+                        (true, false) => [32, 32, 32, 255],
+                        (false, _) => material.color(),
+                    };
+
+                    let start = (index * Self::SWATCH_PIXELS + x + y * row_width) * 4;
+                    self.pixels[start..start + 4].copy_from_slice(&color);
+                }
+            }
+        }
+
+        self.sprite.upload_pixels(&self.pixels);
+    }
+
+    pub fn draw<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        self.sprite.draw(render_pass);
+    }
+}
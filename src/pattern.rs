@@ -0,0 +1,197 @@
+use std::{fs, io, path::Path};
+
+use crate::cell::{Cell, CellGrid, CellType};
+
+pub const EMPTY_SYMBOL: char = '.';
+
+#[derive(Clone)]
+pub struct Pattern {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<CellType>>,
+}
+
+impl Pattern {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![None; width * height],
+        }
+    }
+
+    pub fn copy(grid: &impl CellGrid, from: (i32, i32), to: (i32, i32)) -> Self {
+        let (min_x, min_y) = (from.0.min(to.0), from.1.min(to.1));
+        let width = (from.0 - to.0).unsigned_abs() as usize + 1;
+        let height = (from.1 - to.1).unsigned_abs() as usize + 1;
+
+        let mut pattern = Self::new(width, height);
+        for x in 0..width {
+            for y in 0..height {
+                let cell = grid.get_cell(min_x + x as i32, min_y + y as i32);
+                pattern.set(x, y, cell.map(|cell| cell.cell_type));
+            }
+        }
+
+        pattern
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|row| !row.is_empty())
+            .collect();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut pattern = Self::new(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, symbol) in row.chars().enumerate() {
+                if symbol == EMPTY_SYMBOL {
+                    continue;
+                }
+
+                let cell_type = CellType::from_symbol(symbol).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown cell symbol {symbol:?} at ({x}, {y})"),
+                    )
+                })?;
+                pattern.set(x, y, Some(cell_type));
+            }
+        }
+
+        Ok(pattern)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                text.push(self.get(x, y).map_or(EMPTY_SYMBOL, CellType::symbol));
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<CellType> {
+        self.cells[x + y * self.width]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, cell_type: Option<CellType>) {
+        self.cells[x + y * self.width] = cell_type;
+    }
+
+    pub fn cells(&self, origin: (i32, i32)) -> impl Iterator<Item = ((i32, i32), CellType)> + '_ {
+        (0..self.height).flat_map(move |y| {
+            (0..self.width).filter_map(move |x| {
+                self.get(x, y)
+                    .map(|cell_type| ((origin.0 + x as i32, origin.1 + y as i32), cell_type))
+            })
+        })
+    }
+
+    pub fn paste(&self, grid: &mut impl CellGrid, origin: (i32, i32), update_counter: usize) {
+        for ((x, y), cell_type) in self.cells(origin) {
+            grid.set_cell(
+                x,
+                y,
+                Some(Cell {
+                    cell_type,
+                    last_update: update_counter,
+                }),
+            );
+        }
+    }
+
+    pub fn rotate_clockwise(&self) -> Self {
+        let mut rotated = Self::new(self.height, self.width);
+        for x in 0..self.width {
+            for y in 0..self.height {
+                rotated.set(self.height - 1 - y, x, self.get(x, y));
+            }
+        }
+
+        rotated
+    }
+
+    pub fn flip_horizontal(&self) -> Self {
+        let mut flipped = Self::new(self.width, self.height);
+        for x in 0..self.width {
+            for y in 0..self.height {
+                flipped.set(self.width - 1 - x, y, self.get(x, y));
+            }
+        }
+
+        flipped
+    }
+
+    pub fn flip_vertical(&self) -> Self {
+        let mut flipped = Self::new(self.width, self.height);
+        for x in 0..self.width {
+            for y in 0..self.height {
+                flipped.set(x, self.height - 1 - y, self.get(x, y));
+            }
+        }
+
+        flipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pattern;
+
+    fn transform(pattern: &str, transform: fn(&Pattern) -> Pattern) -> String {
+        transform(&Pattern::parse(pattern).unwrap()).to_text()
+    }
+
+    #[test]
+    fn rotate_clockwise() {
+        assert_eq!(
+            transform("SW.\n..#\n", Pattern::rotate_clockwise),
+            ".S\n.W\n#.\n"
+        );
+    }
+
+    #[test]
+    fn four_rotations_restore_the_pattern() {
+        let pattern = Pattern::parse("SW.\n..#\n").unwrap();
+        let rotated = (0..4).fold(pattern.clone(), |pattern, _| pattern.rotate_clockwise());
+        assert_eq!(rotated.to_text(), pattern.to_text());
+    }
+
+    #[test]
+    fn flip_horizontal() {
+        assert_eq!(
+            transform("SW.\n..#\n", Pattern::flip_horizontal),
+            ".WS\n#..\n"
+        );
+    }
+
+    #[test]
+    fn flip_vertical() {
+        assert_eq!(
+            transform("SW.\n..#\n", Pattern::flip_vertical),
+            "..#\nSW.\n"
+        );
+    }
+}
@@ -3,7 +3,6 @@ use wgpu::util::DeviceExt;
 use crate::render::{drawable::Drawable, pipeline::Vertex};
 
 pub struct Quad {
-    pub position: cgmath::Point2<f32>,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
 }
@@ -54,7 +53,6 @@ impl Quad {
         });
 
         Self {
-            position: cgmath::Point2::new(0.0, 0.0),
             vertex_buffer,
             index_buffer,
         }
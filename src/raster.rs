@@ -0,0 +1,66 @@
+pub fn line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let dx = (to.0 - from.0).abs();
+    let dy = -(to.1 - from.1).abs();
+    let step_x = if from.0 < to.0 { 1 } else { -1 };
+    let step_y = if from.1 < to.1 { 1 } else { -1 };
+
+    let mut points = Vec::with_capacity((dx.max(-dy) + 1) as usize);
+    let (mut x, mut y) = from;
+    let mut error = dx + dy;
+    loop {
+        points.push((x, y));
+        if (x, y) == to {
+            break;
+        }
+
+        let doubled_error = 2 * error;
+        if doubled_error >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled_error <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+
+    points
+}
+
+pub fn rectangle(from: (i32, i32), to: (i32, i32), filled: bool) -> Vec<(i32, i32)> {
+    let (min_x, max_x) = (from.0.min(to.0), from.0.max(to.0));
+    let (min_y, max_y) = (from.1.min(to.1), from.1.max(to.1));
+
+    let mut points = Vec::new();
+    for x in min_x..=max_x {
+        for y in min_y..=max_y {
+            if filled || x == min_x || x == max_x || y == min_y || y == max_y {
+                points.push((x, y));
+            }
+        }
+    }
+
+    points
+}
+
+pub fn circle(center: (i32, i32), radius: i32, filled: bool) -> Vec<(i32, i32)> {
+    let inside = |dx: i32, dy: i32| dx * dx + dy * dy <= radius * radius + radius;
+
+    let mut points = Vec::new();
+    for dx in -radius..=radius {
+        for dy in -radius..=radius {
+            if !inside(dx, dy) {
+                continue;
+            }
+
+            let on_edge = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .iter()
+                .any(|(nx, ny)| !inside(dx + nx, dy + ny));
+            if filled || on_edge {
+                points.push((center.0 + dx, center.1 + dy));
+            }
+        }
+    }
+
+    points
+}
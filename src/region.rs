@@ -0,0 +1,477 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
+};
+
+use crate::{
+    chunk::Chunk,
+    save::{decode_chunk, encode_chunk, read_chunk_bytes},
+};
+
+pub const REGION_SIZE: i32 = 16;
+
+const MAGIC: [u8; 4] = *b"FSRG";
+const VERSION: u32 = 2;
+const SLOTS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+
+type Positioned<T> = ((i32, i32), T);
+type Slots = Vec<Option<Vec<u8>>>;
+type PreservedRegions = Mutex<HashMap<(i32, i32), Slots>>;
+
+#[derive(Clone)]
+pub struct RegionStore {
+    directory: PathBuf,
+    snapshots: Arc<Mutex<Vec<Weak<PreservedRegions>>>>,
+    // For a snapshot, the contents of every region that changed after it was taken.
+    preserved: Option<Arc<PreservedRegions>>,
+}
+
+impl RegionStore {
+    pub const EXTENSION: &'static str = "fsr";
+
+    pub fn new(directory: impl AsRef<Path>) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory: directory.as_ref().to_path_buf(),
+            snapshots: Arc::default(),
+            preserved: None,
+        })
+    }
+
+    /// Returns a read-only view of the regions as they are now. Until the view is dropped, this
+    /// store keeps a copy of each region for it before rewriting or removing the region's file.
+    pub fn snapshot(&self) -> RegionStore {
+        let preserved = Arc::new(Mutex::new(HashMap::new()));
+        let mut snapshots = lock(&self.snapshots);
+        snapshots.retain(|snapshot| snapshot.strong_count() > 0);
+        snapshots.push(Arc::downgrade(&preserved));
+
+        Self {
+            directory: self.directory.clone(),
+            snapshots: self.snapshots.clone(),
+            preserved: Some(preserved),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn region_of((chunk_x, chunk_y): (i32, i32)) -> (i32, i32) {
+        (
+            chunk_x.div_euclid(REGION_SIZE),
+            chunk_y.div_euclid(REGION_SIZE),
+        )
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        for region in self.regions()? {
+            self.modify_region(region, |path| fs::remove_file(path))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn replace_with(&self, other: RegionStore) -> io::Result<()> {
+        self.clear()?;
+        for region in other.regions()? {
+            let from = other.region_path(region);
+            self.modify_region(region, |path| fs::rename(&from, path))?;
+        }
+
+        fs::remove_dir(other.directory)
+    }
+
+    pub fn regions(&self) -> io::Result<Vec<(i32, i32)>> {
+        let preserved = self.preserved.as_deref().map(lock);
+        let mut regions: BTreeSet<_> = self
+            .region_paths()?
+            .iter()
+            .filter_map(|path| Self::parse_region_name(path))
+            .collect();
+        if let Some(preserved) = &preserved {
+            regions.extend(preserved.keys().copied());
+        }
+
+        Ok(regions.into_iter().collect())
+    }
+
+    pub fn stored_chunks(&self) -> io::Result<Vec<(i32, i32)>> {
+        let mut positions = Vec::new();
+        for region in self.regions()? {
+            let slots = self.read_region(region)?;
+            positions.extend(
+                slots
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, slot)| slot.is_some())
+                    .map(|(slot, _)| Self::slot_position(region, slot)),
+            );
+        }
+
+        Ok(positions)
+    }
+
+    pub fn load_chunk(&self, position: (i32, i32)) -> io::Result<Option<Chunk>> {
+        let slots = self.read_region(Self::region_of(position))?;
+        slots[Self::slot(position)]
+            .as_deref()
+            .map(decode_chunk)
+            .transpose()
+    }
+
+    pub fn load_region(&self, region: (i32, i32)) -> io::Result<Vec<((i32, i32), Chunk)>> {
+        self.read_region(region)?
+            .into_iter()
+            .enumerate()
+            .filter_map(|(slot, bytes)| Some((Self::slot_position(region, slot), bytes?)))
+            .map(|(position, bytes)| Ok((position, decode_chunk(&bytes)?)))
+            .collect()
+    }
+
+    pub fn load_chunks(
+        &self,
+        positions: impl IntoIterator<Item = (i32, i32)>,
+    ) -> io::Result<Vec<((i32, i32), Chunk)>> {
+        let mut chunks = Vec::new();
+        for (region, positions) in Self::group_by_region(positions.into_iter().map(|p| (p, ()))) {
+            let slots = self.read_region(region)?;
+            for (position, ()) in positions {
+                if let Some(bytes) = &slots[Self::slot(position)] {
+                    chunks.push((position, decode_chunk(bytes)?));
+                }
+            }
+        }
+
+        Ok(chunks)
+    }
+
+    pub fn save_chunks<'a>(
+        &self,
+        chunks: impl IntoIterator<Item = ((i32, i32), &'a Chunk)>,
+    ) -> io::Result<()> {
+        let encoded = chunks
+            .into_iter()
+            .map(|(position, chunk)| {
+                let bytes = if chunk.is_empty() {
+                    None
+                } else {
+                    Some(encode_chunk(chunk)?)
+                };
+                Ok((position, bytes))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        self.save_encoded(encoded)
+    }
+
+    pub fn save_encoded(
+        &self,
+        chunks: impl IntoIterator<Item = ((i32, i32), Option<Vec<u8>>)>,
+    ) -> io::Result<()> {
+        for (region, chunks) in Self::group_by_region(chunks) {
+            let mut slots = self.read_region_file(region)?;
+            for (position, bytes) in chunks {
+                slots[Self::slot(position)] = bytes;
+            }
+            self.write_region(region, &slots)?;
+        }
+
+        Ok(())
+    }
+
+    fn group_by_region<T>(
+        chunks: impl IntoIterator<Item = ((i32, i32), T)>,
+    ) -> BTreeMap<(i32, i32), Vec<Positioned<T>>> {
+        let mut regions: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (position, value) in chunks {
+            regions
+                .entry(Self::region_of(position))
+                .or_default()
+                .push((position, value));
+        }
+
+        regions
+    }
+
+    fn read_region(&self, region: (i32, i32)) -> io::Result<Slots> {
+        let Some(preserved) = &self.preserved else {
+            return self.read_region_file(region);
+        };
+
+        // The lock is held while reading so the store cannot change the file in between.
+        let preserved = lock(preserved);
+        match preserved.get(&region) {
+            Some(slots) => Ok(slots.clone()),
+            None => self.read_region_file(region),
+        }
+    }
+
+    fn read_region_file(&self, region: (i32, i32)) -> io::Result<Slots> {
+        let path = self.region_path(region);
+        if !path.exists() {
+            return Ok(vec![None; SLOTS]);
+        }
+
+        let mut reader = BufReader::new(fs::File::open(&path)?);
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if header[0..4] != MAGIC || header[4..8] != VERSION.to_le_bytes() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not a version {VERSION} region file", path),
+            ));
+        }
+
+        let mut lengths = Vec::with_capacity(SLOTS);
+        for _ in 0..SLOTS {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            lengths.push(u32::from_le_bytes(bytes) as usize);
+        }
+
+        lengths
+            .into_iter()
+            .map(|length| {
+                if length == 0 {
+                    return Ok(None);
+                }
+
+                read_chunk_bytes(&mut reader, length).map(Some)
+            })
+            .collect()
+    }
+
+    fn write_region(&self, region: (i32, i32), slots: &[Option<Vec<u8>>]) -> io::Result<()> {
+        self.modify_region(region, |path| {
+            if slots.iter().all(Option::is_none) {
+                return match fs::remove_file(path) {
+                    Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+                    _ => Ok(()),
+                };
+            }
+
+            let temporary_path = path.with_extension("tmp");
+            let mut writer = BufWriter::new(fs::File::create(&temporary_path)?);
+            writer.write_all(&MAGIC)?;
+            writer.write_all(&VERSION.to_le_bytes())?;
+            for slot in slots {
+                let length = slot.as_ref().map_or(0, |bytes| bytes.len() as u32);
+                writer.write_all(&length.to_le_bytes())?;
+            }
+            for bytes in slots.iter().flatten() {
+                writer.write_all(bytes)?;
+            }
+            writer.flush()?;
+            drop(writer);
+
+            fs::rename(temporary_path, path)
+        })
+    }
+
+    fn modify_region(
+        &self,
+        region: (i32, i32),
+        modify: impl FnOnce(&Path) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if self.preserved.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Region snapshots are read-only",
+            ));
+        }
+
+        let snapshots: Vec<_> = lock(&self.snapshots)
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        let mut preserved: Vec<_> = snapshots.iter().map(|snapshot| lock(snapshot)).collect();
+        if preserved
+            .iter()
+            .any(|regions| !regions.contains_key(&region))
+        {
+            let slots = self.read_region_file(region)?;
+            for regions in &mut preserved {
+                regions.entry(region).or_insert_with(|| slots.clone());
+            }
+        }
+
+        modify(&self.region_path(region))
+    }
+
+    fn region_paths(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) == Some(Self::EXTENSION) {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    fn region_path(&self, (region_x, region_y): (i32, i32)) -> PathBuf {
+        self.directory
+            .join(format!("r.{region_x}.{region_y}.{}", Self::EXTENSION))
+    }
+
+    fn parse_region_name(path: &Path) -> Option<(i32, i32)> {
+        let name = path.file_stem()?.to_str()?;
+        let mut parts = name.strip_prefix("r.")?.split('.');
+        let region = (parts.next()?.parse().ok()?, parts.next()?.parse().ok()?);
+        parts.next().is_none().then_some(region)
+    }
+
+    fn slot((chunk_x, chunk_y): (i32, i32)) -> usize {
+        (chunk_x.rem_euclid(REGION_SIZE) + chunk_y.rem_euclid(REGION_SIZE) * REGION_SIZE) as usize
+    }
+
+    fn slot_position((region_x, region_y): (i32, i32), slot: usize) -> (i32, i32) {
+        (
+            region_x * REGION_SIZE + slot as i32 % REGION_SIZE,
+            region_y * REGION_SIZE + slot as i32 / REGION_SIZE,
+        )
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{
+        cell::{Cell, CellGrid, CellType},
+        world::World,
+    };
+
+    fn store(name: &str) -> RegionStore {
+        let directory =
+            env::temp_dir().join(format!("falling-sand-region-{name}-{}", std::process::id()));
+        let regions = RegionStore::new(directory).unwrap();
+        regions.clear().unwrap();
+        regions
+    }
+
+    fn chunk(text: &str) -> Chunk {
+        Chunk::parse(text).unwrap().0
+    }
+
+    #[test]
+    fn negative_chunks_round_trip() {
+        let regions = store("negative");
+        let positions = [(-1, -1), (-16, -17), (-17, 0), (15, -16)];
+        let mut chunks: Vec<_> = positions
+            .iter()
+            .enumerate()
+            .map(|(index, &position)| (position, chunk(&"S".repeat(index + 1))))
+            .collect();
+        regions
+            .save_chunks(chunks.iter().map(|(position, chunk)| (*position, chunk)))
+            .unwrap();
+
+        assert_eq!(RegionStore::region_of((-1, -1)), (-1, -1));
+        assert_eq!(RegionStore::region_of((-17, 0)), (-2, 0));
+        assert_eq!(
+            regions.regions().unwrap(),
+            [(-2, 0), (-1, -2), (-1, -1), (0, -1)]
+        );
+        let mut stored = regions.stored_chunks().unwrap();
+        stored.sort();
+        let mut expected = positions.to_vec();
+        expected.sort();
+        assert_eq!(stored, expected);
+        for (position, chunk) in &chunks {
+            assert_eq!(regions.load_chunk(*position).unwrap().as_ref(), Some(chunk));
+        }
+        let mut loaded = regions.load_chunks(positions).unwrap();
+        loaded.sort_by_key(|(position, _)| *position);
+        chunks.sort_by_key(|(position, _)| *position);
+        assert_eq!(loaded, chunks);
+
+        fs::remove_dir_all(regions.directory()).unwrap();
+    }
+
+    #[test]
+    fn evicted_chunks_reload() {
+        let regions = store("evict");
+        let mut world = World::streamed(regions.clone(), 0, 0);
+        let sand = Some(Cell {
+            cell_type: CellType::Sand,
+            last_update: 7,
+        });
+        world.stream((-100, -100), 1).unwrap();
+        world.set_cell(-100, -100, sand);
+        world.set_cell(-129, -65, sand);
+
+        world.stream((1000, 1000), 1).unwrap();
+        assert!(!world.is_loaded(-2, -2));
+        assert_eq!(regions.stored_chunks().unwrap(), [(-3, -2), (-2, -2)]);
+
+        world.stream((-100, -100), 1).unwrap();
+        assert_eq!(world.get_cell(-100, -100), sand);
+        assert_eq!(world.get_cell(-129, -65), sand);
+        assert_eq!(world.get_cell(-99, -100), None);
+
+        fs::remove_dir_all(regions.directory()).unwrap();
+    }
+
+    #[test]
+    fn snapshots_keep_old_regions() {
+        let regions = store("snapshot");
+        let (old, new) = (chunk("S"), chunk("W"));
+        regions
+            .save_chunks([((3, 3), &old), ((-1, 0), &old)])
+            .unwrap();
+
+        let snapshot = regions.snapshot();
+        regions.save_chunks([((3, 3), &new)]).unwrap();
+        assert_eq!(regions.load_chunk((3, 3)).unwrap().as_ref(), Some(&new));
+        assert_eq!(snapshot.load_chunk((3, 3)).unwrap().as_ref(), Some(&old));
+
+        regions.save_chunks([((3, 3), &Chunk::new())]).unwrap();
+        assert_eq!(regions.regions().unwrap(), [(-1, 0)]);
+        assert_eq!(snapshot.regions().unwrap(), [(-1, 0), (0, 0)]);
+        assert_eq!(snapshot.load_chunk((3, 3)).unwrap().as_ref(), Some(&old));
+        assert_eq!(snapshot.load_chunk((-1, 0)).unwrap().as_ref(), Some(&old));
+
+        let error = snapshot.save_chunks([((3, 3), &new)]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+
+        let later = regions.snapshot();
+        drop(snapshot);
+        regions.clear().unwrap();
+        assert_eq!(later.load_chunk((-1, 0)).unwrap().as_ref(), Some(&old));
+        assert_eq!(later.load_chunk((3, 3)).unwrap(), None);
+
+        fs::remove_dir_all(regions.directory()).unwrap();
+    }
+
+    #[test]
+    fn clear_and_replace_with() {
+        let (regions, other) = (store("replace"), store("replace-other"));
+        let (old, new) = (chunk("S"), chunk("W"));
+        regions
+            .save_chunks([((0, 0), &old), ((40, 0), &old)])
+            .unwrap();
+        other.save_chunks([((-20, 5), &new)]).unwrap();
+
+        regions.replace_with(other.clone()).unwrap();
+        assert!(!other.directory().exists());
+        assert_eq!(regions.stored_chunks().unwrap(), [(-20, 5)]);
+        assert_eq!(regions.load_chunk((-20, 5)).unwrap().as_ref(), Some(&new));
+        assert_eq!(regions.load_chunk((0, 0)).unwrap(), None);
+
+        regions.clear().unwrap();
+        assert!(regions.regions().unwrap().is_empty());
+        assert!(regions.stored_chunks().unwrap().is_empty());
+
+        fs::remove_dir_all(regions.directory()).unwrap();
+    }
+}
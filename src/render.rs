@@ -1,3 +1,4 @@
 pub mod drawable;
 pub mod pipeline;
 pub mod renderer;
+pub mod sprite;
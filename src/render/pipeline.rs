@@ -3,10 +3,12 @@ use std::{borrow::Cow, cell::RefCell, rc::Rc};
 use crate::{
     camera::{Camera, CameraUniform},
     texture::Texture,
-    ModelUniform,
 };
 
-use super::renderer::Renderer;
+use super::{
+    renderer::Renderer,
+    sprite::{ModelUniform, Sprite},
+};
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -41,16 +43,13 @@ pub struct RenderPipeline2D {
     renderer: Rc<RefCell<Renderer>>,
     render_pipeline: wgpu::RenderPipeline,
 
-    pub texture: Texture,
-    texture_bind_group: wgpu::BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
 
     camera_uniform: CameraUniform,
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
 
-    model_uniform: ModelUniform,
-    model_buffer: wgpu::Buffer,
-    model_bind_group: wgpu::BindGroup,
+    model_bind_group_layout: wgpu::BindGroupLayout,
 }
 
 impl RenderPipeline2D {
@@ -64,14 +63,12 @@ impl RenderPipeline2D {
                 source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
             });
 
-        let (texture, texture_bind_group_layout, texture_bind_group) =
-            Self::create_texture(renderer.clone());
+        let texture_bind_group_layout = Self::create_texture_bind_group_layout(renderer.clone());
 
         let (camera_uniform, camera_buffer, camera_bind_group_layout, camera_bind_group) =
             Self::create_camera_buffer(renderer.clone());
 
-        let (model_uniform, model_buffer, model_bind_group_layout, model_bind_group) =
-            Self::create_model_buffer(renderer.clone());
+        let model_bind_group_layout = Self::create_model_bind_group_layout(renderer.clone());
 
         let pipeline_layout =
             renderer
@@ -118,66 +115,57 @@ impl RenderPipeline2D {
             renderer,
             render_pipeline,
 
-            texture,
-            texture_bind_group,
+            texture_bind_group_layout,
 
             camera_uniform,
             camera_buffer,
             camera_bind_group,
 
-            model_uniform,
-            model_buffer,
-            model_bind_group,
+            model_bind_group_layout,
         }
     }
 
-    fn create_texture(
-        renderer: Rc<RefCell<Renderer>>,
-    ) -> (Texture, wgpu::BindGroupLayout, wgpu::BindGroup) {
-        let texture = Texture::new(&renderer.borrow().device, 64, 64);
-
-        let texture_bind_group_layout =
-            renderer
-                .borrow()
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("Texture Bind Group Layout"),
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: texture.texture_binding(),
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: texture.sampler_binding(),
-                            count: None,
-                        },
-                    ],
-                });
-
-        let texture_bind_group =
-            renderer
-                .borrow()
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Texture Bind Group"),
-                    layout: &texture_bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::TextureView(&texture.view),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::Sampler(&texture.sampler),
-                        },
-                    ],
-                });
+    fn create_texture_bind_group_layout(renderer: Rc<RefCell<Renderer>>) -> wgpu::BindGroupLayout {
+        renderer
+            .borrow()
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Texture Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: Texture::texture_binding(),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: Texture::sampler_binding(),
+                        count: None,
+                    },
+                ],
+            })
+    }
 
-        (texture, texture_bind_group_layout, texture_bind_group)
+    pub fn create_texture_bind_group(&self, texture: &Texture) -> wgpu::BindGroup {
+        self.renderer
+            .borrow()
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Texture Bind Group"),
+                layout: &self.texture_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                    },
+                ],
+            })
     }
 
     fn create_camera_buffer(
@@ -239,17 +227,30 @@ impl RenderPipeline2D {
         )
     }
 
-    fn create_model_buffer(
-        renderer: Rc<RefCell<Renderer>>,
-    ) -> (
-        ModelUniform,
-        wgpu::Buffer,
-        wgpu::BindGroupLayout,
-        wgpu::BindGroup,
-    ) {
+    fn create_model_bind_group_layout(renderer: Rc<RefCell<Renderer>>) -> wgpu::BindGroupLayout {
+        renderer
+            .borrow()
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Model Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            })
+    }
+
+    pub fn create_model_buffer(&self) -> (ModelUniform, wgpu::Buffer, wgpu::BindGroup) {
         let model_uniform = ModelUniform::new();
 
-        let model_buffer = renderer
+        let model_buffer = self
+            .renderer
             .borrow()
             .device
             .create_buffer(&wgpu::BufferDescriptor {
@@ -259,43 +260,24 @@ impl RenderPipeline2D {
                 mapped_at_creation: false,
             });
 
-        let model_bind_group_layout =
-            renderer
-                .borrow()
-                .device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: Some("Model Bind Group Layout"),
-                    entries: &[wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }],
-                });
-
         let model_bind_group =
-            renderer
+            self.renderer
                 .borrow()
                 .device
                 .create_bind_group(&wgpu::BindGroupDescriptor {
                     label: Some("Model Bind Group"),
-                    layout: &model_bind_group_layout,
+                    layout: &self.model_bind_group_layout,
                     entries: &[wgpu::BindGroupEntry {
                         binding: 0,
                         resource: model_buffer.as_entire_binding(),
                     }],
                 });
 
-        (
-            model_uniform,
-            model_buffer,
-            model_bind_group_layout,
-            model_bind_group,
-        )
+        (model_uniform, model_buffer, model_bind_group)
+    }
+
+    pub fn create_sprite(&self, texture_size: (usize, usize), quad_size: (usize, usize)) -> Sprite {
+        Sprite::new(self.renderer.clone(), self, texture_size, quad_size)
     }
 }
 
@@ -309,19 +291,8 @@ impl RenderPipeline2D {
         );
     }
 
-    pub fn update_model(&mut self, position: cgmath::Point3<f32>) {
-        self.model_uniform.update_model(position);
-        self.renderer.borrow().queue.write_buffer(
-            &self.model_buffer,
-            0,
-            bytemuck::cast_slice(&[self.model_uniform]),
-        );
-    }
-
     pub fn prepare<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
         render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
         render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-        render_pass.set_bind_group(2, &self.model_bind_group, &[]);
     }
 }
@@ -1,35 +1,89 @@
-use std::sync::Arc;
+use std::{io, sync::Arc};
+
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+pub enum RenderTarget {
+    Window(Arc<winit::window::Window>),
+    Offscreen { width: u32, height: u32 },
+}
+
+enum Output {
+    Surface {
+        _window: Arc<winit::window::Window>,
+        surface: wgpu::Surface<'static>,
+        surface_config: wgpu::SurfaceConfiguration,
+    },
+    Offscreen {
+        texture: wgpu::Texture,
+    },
+}
+
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+pub struct Capture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+}
 
 pub struct Frame {
-    pub texture: wgpu::SurfaceTexture,
+    pub texture: Option<wgpu::SurfaceTexture>,
+    // Set when the frame is drawn to a texture of its own instead of the output.
+    offscreen: Option<wgpu::Texture>,
     pub view: wgpu::TextureView,
     pub encoder: wgpu::CommandEncoder,
 }
 
 pub struct Renderer {
-    _window: Arc<winit::window::Window>,
     _instance: wgpu::Instance,
-    _adapter: wgpu::Adapter,
-    pub surface: wgpu::Surface<'static>,
-    surface_config: wgpu::SurfaceConfiguration,
+    adapter: wgpu::Adapter,
+    output: Output,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub swapchain_format: wgpu::TextureFormat,
 }
 
 impl Renderer {
-    pub async fn new(window: Arc<winit::window::Window>) -> Self {
+    pub async fn new(target: RenderTarget, force_fallback_adapter: bool) -> io::Result<Self> {
         let instance = wgpu::Instance::default();
 
-        let surface = instance.create_surface(window.clone()).unwrap();
-        let adapter = instance
+        let surface = match &target {
+            RenderTarget::Window(window) => Some(
+                instance
+                    .create_surface(window.clone())
+                    .map_err(io::Error::other)?,
+            ),
+            RenderTarget::Offscreen { .. } => None,
+        };
+
+        let mut adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
+                force_fallback_adapter,
+                compatible_surface: surface.as_ref(),
             })
-            .await
-            .expect("Failed to find an appropriate adapter");
+            .await;
+        if adapter.is_none() && !force_fallback_adapter {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    force_fallback_adapter: true,
+                    compatible_surface: surface.as_ref(),
+                })
+                .await;
+        }
+        let adapter = adapter.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Failed to find an appropriate adapter",
+            )
+        })?;
 
         let (device, queue) = adapter
             .request_device(
@@ -42,53 +96,124 @@ impl Renderer {
                 None,
             )
             .await
-            .expect("Failed to create device");
+            .map_err(io::Error::other)?;
 
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
+        let (output, swapchain_format) = match (target, surface) {
+            (RenderTarget::Window(window), Some(surface)) => {
+                let size = window.inner_size();
+                let mut surface_config = surface
+                    .get_default_config(&adapter, size.width, size.height)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::Unsupported,
+                            "The adapter cannot present to the window",
+                        )
+                    })?;
+                let swapchain_capabilities = surface.get_capabilities(&adapter);
+                let swapchain_format = surface_config.format;
+                if swapchain_capabilities
+                    .usages
+                    .contains(wgpu::TextureUsages::COPY_SRC)
+                {
+                    surface_config.usage |= wgpu::TextureUsages::COPY_SRC;
+                }
+                surface.configure(&device, &surface_config);
 
-        let size = window.inner_size();
-        let surface_config = surface
-            .get_default_config(&adapter, size.width, size.height)
-            .unwrap();
-        surface.configure(&device, &surface_config);
+                let output = Output::Surface {
+                    _window: window,
+                    surface,
+                    surface_config,
+                };
+                (output, swapchain_format)
+            }
+            (RenderTarget::Offscreen { width, height }, _) => {
+                let texture =
+                    Self::create_offscreen_texture(&device, width, height, OFFSCREEN_FORMAT);
+                (Output::Offscreen { texture }, OFFSCREEN_FORMAT)
+            }
+            (RenderTarget::Window(_), None) => unreachable!(),
+        };
 
-        Self {
-            _window: window,
+        Ok(Self {
             _instance: instance,
-            _adapter: adapter,
-            surface,
-            surface_config,
+            adapter,
+            output,
             device,
             queue,
             swapchain_format,
-        }
+        })
+    }
+
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        self.surface_config.width = new_size.width.max(1);
-        self.surface_config.height = new_size.height.max(1);
-        self.surface.configure(&self.device, &self.surface_config);
+        let (width, height) = (new_size.width.max(1), new_size.height.max(1));
+        match &mut self.output {
+            Output::Surface {
+                surface,
+                surface_config,
+                ..
+            } => {
+                surface_config.width = width;
+                surface_config.height = height;
+                surface.configure(&self.device, surface_config);
+            }
+            Output::Offscreen { texture } => {
+                *texture =
+                    Self::create_offscreen_texture(&self.device, width, height, OFFSCREEN_FORMAT);
+            }
+        }
     }
 
     pub fn begin_render(&mut self) -> Option<Frame> {
-        if let Ok(frame) = self.surface.get_current_texture() {
-            let view = frame
-                .texture
-                .create_view(&wgpu::TextureViewDescriptor::default());
-
-            let encoder = self
-                .device
-                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-            return Some(Frame {
-                texture: frame,
-                view,
-                encoder,
-            });
-        }
+        let (texture, view) = match &self.output {
+            Output::Surface { surface, .. } => {
+                let frame = surface.get_current_texture().ok()?;
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            Output::Offscreen { texture } => (
+                None,
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            ),
+        };
+
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        Some(Frame {
+            texture,
+            offscreen: None,
+            view,
+            encoder,
+        })
+    }
+
+    /// Begins a frame drawn to a new texture the size of the output, for capturing a window
+    /// whose surface cannot be copied from.
+    pub fn begin_offscreen_render(&self) -> Frame {
+        let (width, height) = match &self.output {
+            Output::Surface { surface_config, .. } => (surface_config.width, surface_config.height),
+            Output::Offscreen { texture } => (texture.width(), texture.height()),
+        };
+        let texture =
+            Self::create_offscreen_texture(&self.device, width, height, self.swapchain_format);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        None
+        Frame {
+            texture: None,
+            offscreen: Some(texture),
+            view,
+            encoder,
+        }
     }
 
     pub fn create_default_render_pass<'a: 'b, 'b>(
@@ -113,8 +238,121 @@ impl Renderer {
             })
     }
 
+    pub fn capture(&self, frame: &mut Frame) -> Option<Capture> {
+        let texture = match (&frame.offscreen, &self.output) {
+            (Some(texture), _) => texture,
+            (None, Output::Surface { surface_config, .. }) => {
+                if !surface_config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+                    return None;
+                }
+                &frame.texture.as_ref()?.texture
+            }
+            (None, Output::Offscreen { texture }) => texture,
+        };
+
+        let format = texture.format();
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            return None;
+        }
+
+        let (width, height) = (texture.width(), texture.height());
+        let padded_bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        frame.encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+
+        Some(Capture {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            format,
+        })
+    }
+
+    pub fn read_capture(&self, capture: Capture) -> Option<Screenshot> {
+        let slice = capture.buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+
+        let data = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((capture.width * capture.height * 4) as usize);
+        for row in data.chunks(capture.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..(capture.width * 4) as usize]);
+        }
+        drop(data);
+        capture.buffer.unmap();
+
+        let bgra = matches!(
+            capture.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        for pixel in pixels.chunks_mut(4) {
+            if bgra {
+                pixel.swap(0, 2);
+            }
+            pixel[3] = u8::MAX;
+        }
+
+        Some(Screenshot {
+            width: capture.width,
+            height: capture.height,
+            pixels,
+        })
+    }
+
     pub fn finish_render(&self, frame: Frame) {
         self.queue.submit(Some(frame.encoder.finish()));
-        frame.texture.present();
+        if let Some(texture) = frame.texture {
+            texture.present();
+        }
+    }
+
+    fn create_offscreen_texture(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Render Target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
     }
 }
@@ -0,0 +1,89 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{quad::Quad, texture::Texture};
+
+use super::{drawable::Drawable, pipeline::RenderPipeline2D, renderer::Renderer};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ModelUniform {
+    model: [[f32; 4]; 4],
+}
+
+impl ModelUniform {
+    pub fn new() -> Self {
+        use cgmath::SquareMatrix;
+        Self {
+            model: cgmath::Matrix4::identity().into(),
+        }
+    }
+
+    pub fn update_model(&mut self, position: cgmath::Point3<f32>) {
+        use cgmath::EuclideanSpace;
+        self.model = cgmath::Matrix4::from_translation(position.to_vec()).into()
+    }
+}
+
+impl Default for ModelUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Sprite {
+    renderer: Rc<RefCell<Renderer>>,
+    quad: Quad,
+
+    texture: Texture,
+    texture_bind_group: wgpu::BindGroup,
+
+    model_uniform: ModelUniform,
+    model_buffer: wgpu::Buffer,
+    model_bind_group: wgpu::BindGroup,
+}
+
+impl Sprite {
+    pub fn new(
+        renderer: Rc<RefCell<Renderer>>,
+        pipeline: &RenderPipeline2D,
+        texture_size: (usize, usize),
+        quad_size: (usize, usize),
+    ) -> Self {
+        let quad = Quad::new(&renderer.borrow().device, quad_size);
+        let texture = Texture::new(&renderer.borrow().device, texture_size.0, texture_size.1);
+        let texture_bind_group = pipeline.create_texture_bind_group(&texture);
+        let (model_uniform, model_buffer, model_bind_group) = pipeline.create_model_buffer();
+
+        Self {
+            renderer,
+            quad,
+
+            texture,
+            texture_bind_group,
+
+            model_uniform,
+            model_buffer,
+            model_bind_group,
+        }
+    }
+
+    pub fn update_model(&mut self, position: cgmath::Point3<f32>) {
+        self.model_uniform.update_model(position);
+        self.renderer.borrow().queue.write_buffer(
+            &self.model_buffer,
+            0,
+            bytemuck::cast_slice(&[self.model_uniform]),
+        );
+    }
+
+    pub fn upload_pixels(&self, pixels: &[u8]) {
+        self.texture
+            .upload_pixels(&self.renderer.borrow().queue, pixels);
+    }
+
+    pub fn draw<'a: 'b, 'b>(&'a self, render_pass: &mut wgpu::RenderPass<'b>) {
+        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.model_bind_group, &[]);
+        self.quad.draw(render_pass);
+    }
+}
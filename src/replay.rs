@@ -0,0 +1,394 @@
+use std::{
+    fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    cell::{Cell, CellGrid},
+    chunk::Chunk,
+    region::RegionStore,
+    save::{self, read_u32, read_u64, read_u8},
+    world::World,
+};
+
+pub const MAGIC: [u8; 4] = *b"FSRC";
+pub const VERSION: u32 = 1;
+
+// A recording may not make a world load more chunks than `save::read_world` would.
+const MAX_LOADED_CHUNKS: usize = save::MAX_LOADED_WORLD_BYTES / std::mem::size_of::<Chunk>();
+
+/// The largest radius a recording may stream, so that it loads at most `MAX_LOADED_CHUNKS`.
+pub const MAX_STREAM_RADIUS: usize = 63;
+
+const SET_CELL_TAG: u8 = 0;
+const STREAM_TAG: u8 = 1;
+
+#[derive(Clone, Copy)]
+pub enum Event {
+    SetCell { x: i32, y: i32, cell: Option<Cell> },
+    Stream { center: (i32, i32), radius: usize },
+}
+
+#[derive(Clone, Copy)]
+pub struct Input {
+    pub tick: usize,
+    pub event: Event,
+}
+
+pub struct Recording {
+    world: Vec<u8>,
+    loaded: Vec<(i32, i32)>,
+    inputs: Vec<Input>,
+    last_stream: Option<((i32, i32), usize)>,
+}
+
+impl Recording {
+    pub fn start(world: &World) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        save::write_world(world, &mut bytes)?;
+        let mut loaded: Vec<_> = world
+            .loaded_chunks()
+            .map(|(position, _)| position)
+            .collect();
+        loaded.sort();
+
+        Ok(Self {
+            world: bytes,
+            loaded,
+            inputs: Vec::new(),
+            last_stream: None,
+        })
+    }
+
+    pub fn loaded(&self) -> &[(i32, i32)] {
+        &self.loaded
+    }
+
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    pub fn world(&self) -> io::Result<World> {
+        save::read_world(&mut self.world.as_slice())
+    }
+
+    pub fn world_into(&self, regions: RegionStore) -> io::Result<World> {
+        save::read_world_into(&mut self.world.as_slice(), regions)
+    }
+
+    pub fn record(&mut self, tick: usize, event: Event) {
+        if let Event::Stream { center, radius } = event {
+            if self.last_stream == Some((center, radius)) {
+                return;
+            }
+            self.last_stream = Some((center, radius));
+        }
+
+        self.inputs.push(Input { tick, event });
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.world.len() as u64).to_le_bytes())?;
+        writer.write_all(&self.world)?;
+
+        writer.write_all(&(self.loaded.len() as u64).to_le_bytes())?;
+        for &(chunk_x, chunk_y) in &self.loaded {
+            writer.write_all(&chunk_x.to_le_bytes())?;
+            writer.write_all(&chunk_y.to_le_bytes())?;
+        }
+
+        writer.write_all(&(self.inputs.len() as u64).to_le_bytes())?;
+        for input in &self.inputs {
+            writer.write_all(&(input.tick as u64).to_le_bytes())?;
+            match input.event {
+                Event::SetCell { x, y, cell } => {
+                    writer.write_all(&[SET_CELL_TAG])?;
+                    writer.write_all(&x.to_le_bytes())?;
+                    writer.write_all(&y.to_le_bytes())?;
+                    writer.write_all(&[save::cell_tag(&cell)])?;
+                    if let Some(cell) = cell {
+                        writer.write_all(&(cell.last_update as u64).to_le_bytes())?;
+                    }
+                }
+                Event::Stream { center, radius } => {
+                    writer.write_all(&[STREAM_TAG])?;
+                    writer.write_all(&center.0.to_le_bytes())?;
+                    writer.write_all(&center.1.to_le_bytes())?;
+                    writer.write_all(&(radius as u32).to_le_bytes())?;
+                }
+            }
+        }
+
+        writer.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(BufReader::new(fs::File::open(path)?))
+    }
+
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a falling sand recording",
+            ));
+        }
+
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported recording version {version}, expected version {VERSION}"),
+            ));
+        }
+
+        let length = read_u64(&mut reader)?;
+        let mut world = Vec::new();
+        (&mut reader).take(length).read_to_end(&mut world)?;
+        if world.len() as u64 != length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Recording ends inside the initial world",
+            ));
+        }
+
+        let count = read_u64(&mut reader)?;
+        if count > MAX_LOADED_CHUNKS as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Recording loads {count} chunks, more than the {MAX_LOADED_CHUNKS} allowed"
+                ),
+            ));
+        }
+        let mut loaded = Vec::new();
+        for _ in 0..count {
+            loaded.push((read_u32(&mut reader)? as i32, read_u32(&mut reader)? as i32));
+        }
+
+        let mut inputs = Vec::new();
+        for _ in 0..read_u64(&mut reader)? {
+            let tick = read_u64(&mut reader)? as usize;
+            let event = match read_u8(&mut reader)? {
+                SET_CELL_TAG => {
+                    let x = read_u32(&mut reader)? as i32;
+                    let y = read_u32(&mut reader)? as i32;
+                    let tag = read_u8(&mut reader)?;
+                    let cell = if tag == save::EMPTY_TAG {
+                        None
+                    } else {
+                        Some(Cell {
+                            cell_type: save::cell_type_from_tag(tag)?,
+                            last_update: read_u64(&mut reader)? as usize,
+                        })
+                    };
+                    Event::SetCell { x, y, cell }
+                }
+                STREAM_TAG => {
+                    let center = (read_u32(&mut reader)? as i32, read_u32(&mut reader)? as i32);
+                    let radius = read_u32(&mut reader)? as usize;
+                    if radius > MAX_STREAM_RADIUS {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Recording streams a radius of {radius} chunks"),
+                        ));
+                    }
+                    Event::Stream { center, radius }
+                }
+                tag => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown recording event {tag}"),
+                    ))
+                }
+            };
+            inputs.push(Input { tick, event });
+        }
+
+        Ok(Self {
+            world,
+            loaded,
+            inputs,
+            last_stream: None,
+        })
+    }
+}
+
+pub struct Replay {
+    inputs: Vec<Input>,
+    next: usize,
+}
+
+impl Replay {
+    pub fn new(recording: &Recording) -> Self {
+        Self {
+            inputs: recording.inputs().to_vec(),
+            next: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.inputs.len()
+    }
+
+    pub fn apply(&mut self, world: &mut World) -> io::Result<()> {
+        while let Some(input) = self.inputs.get(self.next) {
+            if input.tick > world.tick() {
+                break;
+            }
+
+            match input.event {
+                Event::SetCell { x, y, cell } => world.set_cell(x, y, cell),
+                Event::Stream { center, radius } => world.stream(center, radius)?,
+            }
+            self.next += 1;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct EditRecorder<'a> {
+    world: &'a mut World,
+    recording: Option<&'a mut Recording>,
+}
+
+impl<'a> EditRecorder<'a> {
+    pub fn new(world: &'a mut World, recording: Option<&'a mut Recording>) -> Self {
+        Self { world, recording }
+    }
+}
+
+impl CellGrid for EditRecorder<'_> {
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        self.world.in_bounds(x, y)
+    }
+
+    fn get_cell(&self, x: i32, y: i32) -> Option<Cell> {
+        self.world.get_cell(x, y)
+    }
+
+    fn set_cell(&mut self, x: i32, y: i32, cell: Option<Cell>) {
+        if !self.world.in_bounds(x, y) {
+            return;
+        }
+
+        self.world.set_cell(x, y, cell);
+        if let Some(recording) = &mut self.recording {
+            recording.record(self.world.tick(), Event::SetCell { x, y, cell });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::{cell::CellType, hash, region::RegionStore};
+
+    fn regions(name: &str) -> RegionStore {
+        let directory = env::temp_dir().join(format!("falling-sand-{name}-{}", std::process::id()));
+        let regions = RegionStore::new(directory).unwrap();
+        regions.clear().unwrap();
+        regions
+    }
+
+    fn recording_bytes(loaded: u64, inputs: u64, events: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(MAGIC);
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(0u64.to_le_bytes());
+        bytes.extend(loaded.to_le_bytes());
+        bytes.extend(inputs.to_le_bytes());
+        bytes.extend(events);
+        bytes
+    }
+
+    #[test]
+    fn oversized_recordings_are_rejected() {
+        let error = |bytes: Vec<u8>| Recording::read(bytes.as_slice()).err().unwrap().kind();
+
+        assert_eq!(
+            error(recording_bytes(1 << 60, 0, &[])),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            error(recording_bytes(0, 1 << 60, &[])),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        let mut stream = vec![0; 8];
+        stream.push(STREAM_TAG);
+        stream.extend([0; 8]);
+        stream.extend((MAX_STREAM_RADIUS as u32 + 1).to_le_bytes());
+        assert_eq!(
+            error(recording_bytes(0, 1, &stream)),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_world() {
+        let (recorded, replayed_regions) = (regions("recorded"), regions("replayed"));
+        let mut world = World::streamed(recorded.clone(), 0, 17);
+        world.stream((0, 0), 2).unwrap();
+        let initial = hash::world_hash(&world).unwrap();
+        let mut recording = Recording::start(&world).unwrap();
+
+        for tick in 0..40 {
+            let mut grid = EditRecorder::new(&mut world, Some(&mut recording));
+            let cell_type = CellType::ALL[tick % CellType::ALL.len()];
+            for x in -3..3 {
+                grid.set_cell(
+                    x * 2 + tick as i32 % 2,
+                    10,
+                    Some(Cell {
+                        cell_type,
+                        last_update: 0,
+                    }),
+                );
+            }
+            if tick == 20 {
+                recording.record(
+                    world.tick(),
+                    Event::Stream {
+                        center: (70, 0),
+                        radius: 2,
+                    },
+                );
+                world.stream((70, 0), 2).unwrap();
+            }
+            world.step();
+        }
+
+        let path = env::temp_dir().join(format!("falling-sand-{}.fsrec", std::process::id()));
+        recording.save(&path).unwrap();
+        let recording = Recording::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let mut replayed = recording.world_into(replayed_regions.clone()).unwrap();
+        replayed.set_loaded(recording.loaded()).unwrap();
+        let mut replay = Replay::new(&recording);
+        while replayed.tick() < world.tick() {
+            replay.apply(&mut replayed).unwrap();
+            replayed.step();
+        }
+
+        assert!(replay.is_finished());
+        assert_ne!(hash::world_hash(&world).unwrap(), initial);
+        assert_eq!(
+            hash::world_hash(&replayed).unwrap(),
+            hash::world_hash(&world).unwrap()
+        );
+
+        fs::remove_dir_all(recorded.directory()).unwrap();
+        fs::remove_dir_all(replayed_regions.directory()).unwrap();
+    }
+}
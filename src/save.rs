@@ -0,0 +1,438 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+use crate::{
+    cell::{Cell, CellType},
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    region::RegionStore,
+    world::World,
+};
+
+pub const MAGIC: [u8; 4] = *b"FSWD";
+pub const VERSION: u32 = 3;
+
+const RAW_CHUNKS_VERSION: u32 = 1;
+const DENSE_CHUNKS_VERSION: u32 = 2;
+
+pub const MAX_WORLD_SIZE: usize = 1 << 16;
+pub const MAX_WORLD_CHUNKS: usize = 1 << 20;
+/// The most memory [`read_world`] may use for chunks.
+pub const MAX_LOADED_WORLD_BYTES: usize = 1 << 30;
+
+pub(crate) const EMPTY_TAG: u8 = 0;
+
+pub const MAX_CHUNK_BYTES: usize = 64 * 1024;
+
+const CHUNK_MARKER: u8 = 1;
+const END_MARKER: u8 = 0;
+
+// Chunks read from a save are written to the region store in batches of this many bytes.
+const REGION_BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+pub fn save_world(world: &World, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    write_world(world, &mut writer)?;
+    writer.flush()
+}
+
+pub fn load_world(path: impl AsRef<Path>) -> io::Result<World> {
+    read_world(&mut BufReader::new(fs::File::open(path)?))
+}
+
+pub fn load_world_into(path: impl AsRef<Path>, regions: RegionStore) -> io::Result<World> {
+    read_world_into(&mut BufReader::new(fs::File::open(path)?), regions)
+}
+
+pub fn write_world(world: &World, writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&(world.tick() as u64).to_le_bytes())?;
+    writer.write_all(&world.seed().to_le_bytes())?;
+
+    world.visit_chunks(|(chunk_x, chunk_y), chunk| {
+        let bytes = encode_chunk(chunk)?;
+        writer.write_all(&[CHUNK_MARKER])?;
+        writer.write_all(&chunk_x.to_le_bytes())?;
+        writer.write_all(&chunk_y.to_le_bytes())?;
+        writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+        writer.write_all(&bytes)
+    })?;
+
+    writer.write_all(&[END_MARKER])
+}
+
+/// Reads a whole world into memory, for tools that work on saves directly. Only the saved
+/// chunks are created, so gaps between them act as walls like the edge of the world.
+pub fn read_world(reader: &mut impl Read) -> io::Result<World> {
+    let mut chunks = HashMap::new();
+    let (tick, seed) = read_chunks(reader, |position, chunk| {
+        if (chunks.len() + 1) * std::mem::size_of::<Chunk>() > MAX_LOADED_WORLD_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("World needs more than {MAX_LOADED_WORLD_BYTES} bytes of memory"),
+            ));
+        }
+
+        chunks.insert(position, chunk);
+        Ok(())
+    })?;
+
+    Ok(World::from_chunks(chunks, tick, seed))
+}
+
+/// Reads a world into `regions`, replacing what they held, and returns it with no chunks
+/// loaded so that it can be streamed in around the camera.
+pub fn read_world_into(reader: &mut impl Read, regions: RegionStore) -> io::Result<World> {
+    regions.clear()?;
+
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    let (tick, seed) = read_chunks(reader, |position, chunk| {
+        if chunk.is_empty() {
+            return Ok(());
+        }
+
+        let bytes = encode_chunk(&chunk)?;
+        batch_bytes += bytes.len();
+        batch.push((position, Some(bytes)));
+        if batch_bytes >= REGION_BATCH_BYTES {
+            regions.save_encoded(batch.drain(..))?;
+            batch_bytes = 0;
+        }
+
+        Ok(())
+    })?;
+    regions.save_encoded(batch)?;
+
+    Ok(World::streamed(regions, tick, seed))
+}
+
+fn read_chunks(
+    reader: &mut impl Read,
+    mut visit: impl FnMut((i32, i32), Chunk) -> io::Result<()>,
+) -> io::Result<(usize, u64)> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a falling sand world file",
+        ));
+    }
+
+    let version = read_u32(reader)?;
+    if version == VERSION {
+        let tick = read_u64(reader)? as usize;
+        let seed = read_u64(reader)?;
+        loop {
+            match read_u8(reader)? {
+                CHUNK_MARKER => {
+                    let position = (read_u32(reader)? as i32, read_u32(reader)? as i32);
+                    let length = read_u32(reader)? as usize;
+                    visit(position, decode_chunk(&read_chunk_bytes(reader, length)?)?)?;
+                }
+                END_MARKER => return Ok((tick, seed)),
+                marker => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Unknown chunk marker {marker}"),
+                    ))
+                }
+            }
+        }
+    }
+
+    if version != DENSE_CHUNKS_VERSION && version != RAW_CHUNKS_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported world file version {version}, expected version {VERSION}"),
+        ));
+    }
+
+    let width = read_u32(reader)? as usize;
+    let height = read_u32(reader)? as usize;
+    if width == 0
+        || height == 0
+        || width > MAX_WORLD_SIZE
+        || height > MAX_WORLD_SIZE
+        || width * height > MAX_WORLD_CHUNKS
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid world size {width}x{height} chunks"),
+        ));
+    }
+    let tick = read_u64(reader)? as usize;
+    let seed = read_u64(reader)?;
+
+    for chunk_y in 0..height as i32 {
+        for chunk_x in 0..width as i32 {
+            let chunk = if version == RAW_CHUNKS_VERSION {
+                read_raw_chunk(reader)?
+            } else {
+                let length = read_u32(reader)? as usize;
+                decode_chunk(&read_chunk_bytes(reader, length)?)?
+            };
+            visit((chunk_x, chunk_y), chunk)?;
+        }
+    }
+
+    Ok((tick, seed))
+}
+
+pub fn encode_chunk(chunk: &Chunk) -> io::Result<Vec<u8>> {
+    let cells = chunk.cells.iter().flatten();
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    for (tag, run) in run_lengths(cells.clone().map(cell_tag)) {
+        encoder.write_all(&[tag])?;
+        encoder.write_all(&run.to_le_bytes())?;
+    }
+    for (last_update, run) in run_lengths(cells.flatten().map(|cell| cell.last_update as u64)) {
+        encoder.write_all(&last_update.to_le_bytes())?;
+        encoder.write_all(&run.to_le_bytes())?;
+    }
+
+    encoder.finish()
+}
+
+pub fn decode_chunk(bytes: &[u8]) -> io::Result<Chunk> {
+    let mut decoder = DeflateDecoder::new(bytes);
+
+    let mut tags = Vec::with_capacity(CHUNK_WIDTH * CHUNK_HEIGHT);
+    while tags.len() < CHUNK_WIDTH * CHUNK_HEIGHT {
+        let tag = read_u8(&mut decoder)?;
+        let run = read_u16(&mut decoder)? as usize;
+        tags.extend(std::iter::repeat_n(tag, run));
+    }
+
+    let filled = tags.iter().filter(|tag| **tag != EMPTY_TAG).count();
+    let mut last_updates = Vec::with_capacity(filled);
+    while last_updates.len() < filled {
+        let last_update = read_u64(&mut decoder)? as usize;
+        let run = read_u16(&mut decoder)? as usize;
+        last_updates.extend(std::iter::repeat_n(last_update, run));
+    }
+
+    if tags.len() != CHUNK_WIDTH * CHUNK_HEIGHT || last_updates.len() != filled {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Chunk runs do not match the chunk size",
+        ));
+    }
+
+    let mut chunk = Chunk::new();
+    let mut last_updates = last_updates.into_iter();
+    for (index, tag) in tags.into_iter().enumerate() {
+        if tag == EMPTY_TAG {
+            continue;
+        }
+
+        chunk.cells[index / CHUNK_HEIGHT][index % CHUNK_HEIGHT] = Some(Cell {
+            cell_type: cell_type_from_tag(tag)?,
+            last_update: last_updates.next().unwrap(),
+        });
+    }
+
+    Ok(chunk)
+}
+
+pub(crate) fn read_chunk_bytes(reader: &mut impl Read, length: usize) -> io::Result<Vec<u8>> {
+    if length > MAX_CHUNK_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Chunk of {length} bytes exceeds the {MAX_CHUNK_BYTES} byte limit"),
+        ));
+    }
+
+    let mut bytes = vec![0; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn run_lengths<T: PartialEq>(values: impl Iterator<Item = T>) -> Vec<(T, u16)> {
+    let mut runs: Vec<(T, u16)> = Vec::new();
+    for value in values {
+        match runs.last_mut() {
+            Some((last, run)) if *last == value && *run < u16::MAX => *run += 1,
+            _ => runs.push((value, 1)),
+        }
+    }
+
+    runs
+}
+
+pub(crate) fn cell_tag(cell: &Option<Cell>) -> u8 {
+    match cell {
+        Some(cell) => cell.cell_type.id() + 1,
+        None => EMPTY_TAG,
+    }
+}
+
+pub(crate) fn cell_type_from_tag(tag: u8) -> io::Result<CellType> {
+    tag.checked_sub(1)
+        .and_then(CellType::from_id)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unknown cell tag {tag}"),
+            )
+        })
+}
+
+fn read_raw_chunk(reader: &mut impl Read) -> io::Result<Chunk> {
+    let mut chunk = Chunk::new();
+    for x in 0..CHUNK_WIDTH {
+        for y in 0..CHUNK_HEIGHT {
+            let tag = read_u8(reader)?;
+            if tag == EMPTY_TAG {
+                continue;
+            }
+
+            chunk.cells[x][y] = Some(Cell {
+                cell_type: cell_type_from_tag(tag)?,
+                last_update: read_u64(reader)? as usize,
+            });
+        }
+    }
+
+    Ok(chunk)
+}
+
+pub(crate) fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filled(cell_type: CellType, last_update: usize) -> Chunk {
+        let mut chunk = Chunk::new();
+        for column in &mut chunk.cells {
+            column.fill(Some(Cell {
+                cell_type,
+                last_update,
+            }));
+        }
+        chunk
+    }
+
+    fn mixed() -> Chunk {
+        let mut chunk = Chunk::new();
+        for x in 0..CHUNK_WIDTH {
+            for y in 0..CHUNK_HEIGHT {
+                if (x * 7 + y * 3) % 5 != 0 {
+                    chunk.cells[x][y] = Some(Cell {
+                        cell_type: CellType::ALL[(x + y) % CellType::ALL.len()],
+                        last_update: x * y,
+                    });
+                }
+            }
+        }
+        chunk
+    }
+
+    fn assert_round_trip(chunk: &Chunk) {
+        let decoded = decode_chunk(&encode_chunk(chunk).unwrap()).unwrap();
+        assert_eq!(&decoded, chunk);
+    }
+
+    #[test]
+    fn empty_chunk_round_trips() {
+        assert_round_trip(&Chunk::new());
+    }
+
+    #[test]
+    fn uniform_chunk_round_trips() {
+        assert_round_trip(&filled(CellType::Stone, 0));
+        assert_round_trip(&filled(CellType::Water, usize::MAX >> 1));
+    }
+
+    #[test]
+    fn mixed_chunk_round_trips() {
+        assert_round_trip(&mixed());
+    }
+
+    #[test]
+    fn world_round_trips() {
+        let world = World::from_chunks([((-1, 2), mixed()), ((0, 2), Chunk::new())], 9, 4);
+
+        let mut bytes = Vec::new();
+        write_world(&world, &mut bytes).unwrap();
+        let loaded = read_world(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!((loaded.tick(), loaded.seed()), (9, 4));
+        for (position, chunk) in world.loaded_chunks() {
+            assert_eq!(loaded.chunk(position.0, position.1), Some(chunk));
+        }
+    }
+
+    #[test]
+    fn unknown_cell_tags_are_rejected() {
+        for tag in [EMPTY_TAG, u8::MAX] {
+            let error = cell_type_from_tag(tag).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn distant_chunks_load_without_filling_the_gap() {
+        let world = World::from_chunks([((0, 0), mixed()), ((1023, 1023), mixed())], 0, 0);
+
+        let mut bytes = Vec::new();
+        write_world(&world, &mut bytes).unwrap();
+        let loaded = read_world(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(loaded.loaded_chunks().count(), 2);
+        assert!(loaded.chunk(512, 512).is_none());
+    }
+
+    #[test]
+    fn raw_chunk_save_still_loads() {
+        let chunk = mixed();
+        let mut bytes = Vec::new();
+        bytes.extend(MAGIC);
+        bytes.extend(RAW_CHUNKS_VERSION.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(12u64.to_le_bytes());
+        bytes.extend(34u64.to_le_bytes());
+        for cell in chunk.cells.iter().flatten() {
+            bytes.push(cell_tag(cell));
+            if let Some(cell) = cell {
+                bytes.extend((cell.last_update as u64).to_le_bytes());
+            }
+        }
+
+        let world = read_world(&mut bytes.as_slice()).unwrap();
+        assert_eq!((world.tick(), world.seed()), (12, 34));
+        assert_eq!(world.chunk(0, 0), Some(&chunk));
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fs, io, path::Path};
+
+use crate::pattern::Pattern;
+
+pub struct Stamp {
+    pub name: String,
+    pub pattern: Pattern,
+}
+
+pub struct StampLibrary {
+    stamps: Vec<Stamp>,
+    selected: usize,
+}
+
+impl StampLibrary {
+    pub const EXTENSION: &'static str = "txt";
+
+    pub fn empty() -> Self {
+        Self {
+            stamps: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn load(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let mut stamps = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(Self::EXTENSION) {
+                continue;
+            }
+
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            match Pattern::load(&path) {
+                Ok(pattern) => stamps.push(Stamp {
+                    name: name.to_string(),
+                    pattern,
+                }),
+                Err(error) => eprintln!("Failed to load stamp {:?}: {}", path, error),
+            }
+        }
+        stamps.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            stamps,
+            selected: 0,
+        })
+    }
+
+    pub fn stamps(&self) -> &[Stamp] {
+        &self.stamps
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected(&self) -> Option<&Stamp> {
+        self.stamps.get(self.selected)
+    }
+
+    pub fn selected_mut(&mut self) -> Option<&mut Stamp> {
+        self.stamps.get_mut(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.stamps.is_empty() {
+            self.selected = (self.selected + 1) % self.stamps.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.stamps.is_empty() {
+            self.selected = (self.selected + self.stamps.len() - 1) % self.stamps.len();
+        }
+    }
+}
@@ -50,7 +50,7 @@ impl Texture {
         }
     }
 
-    pub fn texture_binding(&self) -> wgpu::BindingType {
+    pub fn texture_binding() -> wgpu::BindingType {
         wgpu::BindingType::Texture {
             sample_type: wgpu::TextureSampleType::Float { filterable: true },
             view_dimension: wgpu::TextureViewDimension::D2,
@@ -58,7 +58,7 @@ impl Texture {
         }
     }
 
-    pub fn sampler_binding(&self) -> wgpu::BindingType {
+    pub fn sampler_binding() -> wgpu::BindingType {
         wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering)
     }
 
@@ -69,7 +69,7 @@ impl Texture {
 
         queue.write_texture(
             self.texture.as_image_copy(),
-            &pixels,
+            pixels,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(self.width as u32 * 4),
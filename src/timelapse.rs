@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::image;
+
+pub const GIF_FRAME_DELAY: u16 = 10;
+const GIF_QUANTIZE_SPEED: i32 = 10;
+
+pub struct Timelapse {
+    directory: PathBuf,
+    interval: usize,
+    frame_count: usize,
+    gif_path: Option<PathBuf>,
+    gif: Option<gif::Encoder<BufWriter<fs::File>>>,
+    frame_size: Option<(u32, u32)>,
+}
+
+impl Timelapse {
+    pub fn new(
+        directory: impl AsRef<Path>,
+        interval: usize,
+        gif_path: Option<PathBuf>,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory: directory.as_ref().to_path_buf(),
+            interval: interval.max(1),
+            frame_count: 0,
+            gif_path,
+            gif: None,
+            frame_size: None,
+        })
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn gif_path(&self) -> Option<&Path> {
+        self.gif_path.as_deref()
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn is_due(&self, tick: usize) -> bool {
+        tick.is_multiple_of(self.interval)
+    }
+
+    pub fn add_frame(&mut self, width: u32, height: u32, pixels: &[u8]) -> io::Result<()> {
+        if let Some(size) = self.frame_size.filter(|&size| size != (width, height)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Frame size {width}x{height} does not match the first frame's {}x{}",
+                    size.0, size.1
+                ),
+            ));
+        }
+        self.frame_size = Some((width, height));
+
+        let path = self
+            .directory
+            .join(format!("frame_{:05}.png", self.frame_count));
+        image::write_png(path, width, height, pixels)?;
+        self.frame_count += 1;
+
+        let Some(gif_path) = &self.gif_path else {
+            return Ok(());
+        };
+
+        if self.gif.is_none() {
+            let (Ok(gif_width), Ok(gif_height)) = (u16::try_from(width), u16::try_from(height))
+            else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("A {width}x{height} frame is too large for a GIF"),
+                ));
+            };
+
+            let writer = BufWriter::new(fs::File::create(gif_path)?);
+            let mut encoder =
+                gif::Encoder::new(writer, gif_width, gif_height, &[]).map_err(io::Error::other)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(io::Error::other)?;
+            self.gif = Some(encoder);
+        }
+
+        let Some(encoder) = &mut self.gif else {
+            return Ok(());
+        };
+
+        let mut pixels = pixels.to_vec();
+        let mut frame = gif::Frame::from_rgba_speed(
+            width as u16,
+            height as u16,
+            &mut pixels,
+            GIF_QUANTIZE_SPEED,
+        );
+        frame.delay = GIF_FRAME_DELAY;
+        encoder.write_frame(&frame).map_err(io::Error::other)
+    }
+
+    pub fn finish(self) -> io::Result<usize> {
+        if let Some(encoder) = self.gif {
+            encoder.into_inner().map_err(io::Error::other)?;
+        }
+
+        Ok(self.frame_count)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::raster;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Brush,
+    Fill,
+    Line,
+    Rectangle,
+    Circle,
+    Select,
+    Paste,
+    Stamp,
+}
+
+impl Tool {
+    pub fn shape_cells(self, from: (i32, i32), to: (i32, i32), filled: bool) -> Vec<(i32, i32)> {
+        match self {
+            Tool::Line => raster::line(from, to),
+            Tool::Rectangle => raster::rectangle(from, to, filled),
+            Tool::Circle => {
+                let (dx, dy) = ((to.0 - from.0) as f32, (to.1 - from.1) as f32);
+                let radius = (dx * dx + dy * dy).sqrt().round() as i32;
+                raster::circle(from, radius, filled)
+            }
+            Tool::Brush | Tool::Fill | Tool::Select | Tool::Paste | Tool::Stamp => Vec::new(),
+        }
+    }
+}
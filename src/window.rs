@@ -6,6 +6,7 @@ pub trait Application {
     fn update(&mut self);
     fn draw(&mut self);
     fn handle_input(&mut self, input: &WinitInputHelper);
+    fn exit(&mut self);
 }
 
 pub struct WindowManager {
@@ -21,7 +22,7 @@ impl WindowManager {
         let window = Arc::new(
             winit::window::WindowBuilder::new()
                 .with_title(title)
-                .with_inner_size(winit::dpi::PhysicalSize::new(size.0, size.1))
+                .with_inner_size(winit::dpi::LogicalSize::new(size.0, size.1))
                 .build(&event_loop)
                 .unwrap(),
         );
@@ -46,6 +47,7 @@ impl WindowManager {
 
                 if self.input.update(&event) {
                     if self.input.close_requested() || self.input.destroyed() {
+                        app.exit();
                         elwt.exit();
                         return;
                     }
@@ -0,0 +1,336 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io,
+};
+
+use crate::{
+    cell::{Cell, CellGrid},
+    chunk::{Chunk, CHUNK_HEIGHT, CHUNK_WIDTH},
+    region::RegionStore,
+};
+
+// The first and last cell of an area.
+type CellBounds = ((i32, i32), (i32, i32));
+
+pub struct World {
+    chunks: HashMap<(i32, i32), Chunk>,
+    regions: Option<RegionStore>,
+
+    tick: usize,
+    seed: u64,
+}
+
+impl World {
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self::from_chunks(
+            (0..height as i32)
+                .flat_map(|chunk_y| (0..width as i32).map(move |chunk_x| (chunk_x, chunk_y)))
+                .map(|position| (position, Chunk::new())),
+            0,
+            seed,
+        )
+    }
+
+    pub fn from_chunks(
+        chunks: impl IntoIterator<Item = ((i32, i32), Chunk)>,
+        tick: usize,
+        seed: u64,
+    ) -> Self {
+        Self {
+            chunks: chunks.into_iter().collect(),
+            regions: None,
+
+            tick,
+            seed,
+        }
+    }
+
+    pub fn streamed(regions: RegionStore, tick: usize, seed: u64) -> Self {
+        Self {
+            chunks: HashMap::new(),
+            regions: Some(regions),
+
+            tick,
+            seed,
+        }
+    }
+
+    pub fn regions(&self) -> Option<&RegionStore> {
+        self.regions.as_ref()
+    }
+
+    /// Copies the loaded chunks and takes a snapshot of the regions, so the copy can be saved
+    /// on another thread while this world keeps streaming.
+    pub fn snapshot(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            regions: self.regions.as_ref().map(RegionStore::snapshot),
+
+            tick: self.tick,
+            seed: self.seed,
+        }
+    }
+
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn step(&mut self) {
+        let chunks = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|&position| self.is_simulated(position))
+            .collect();
+        self.update_chunks(chunks, self.tick, self.seed);
+        self.tick += 1;
+    }
+
+    pub fn step_within(&mut self, from: (i32, i32), to: (i32, i32)) {
+        let (min_x, min_y) = (from.0.min(to.0), from.1.min(to.1));
+        let (max_x, max_y) = (from.0.max(to.0), from.1.max(to.1));
+        let chunks = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|&position| self.is_simulated(position))
+            .filter(|&(chunk_x, chunk_y)| {
+                let (x, y) = (chunk_x * CHUNK_WIDTH as i32, chunk_y * CHUNK_HEIGHT as i32);
+                x <= max_x
+                    && x + CHUNK_WIDTH as i32 > min_x
+                    && y <= max_y
+                    && y + CHUNK_HEIGHT as i32 > min_y
+            })
+            .collect();
+        self.update_chunks(chunks, self.tick, self.seed);
+        self.tick += 1;
+    }
+
+    pub fn chunk(&self, chunk_x: i32, chunk_y: i32) -> Option<&Chunk> {
+        self.chunks.get(&(chunk_x, chunk_y))
+    }
+
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = ((i32, i32), &Chunk)> {
+        self.chunks
+            .iter()
+            .map(|(&position, chunk)| (position, chunk))
+    }
+
+    pub fn is_loaded(&self, chunk_x: i32, chunk_y: i32) -> bool {
+        self.chunks.contains_key(&(chunk_x, chunk_y))
+    }
+
+    /// Visits every loaded chunk and every stored chunk that is not loaded, grouped by region
+    /// so that each region file is read at most once.
+    pub fn visit_chunks(
+        &self,
+        mut visit: impl FnMut((i32, i32), &Chunk) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut positions: BTreeSet<_> = self
+            .chunks
+            .keys()
+            .map(|&position| (RegionStore::region_of(position), position))
+            .collect();
+        if let Some(regions) = &self.regions {
+            positions.extend(
+                regions
+                    .stored_chunks()?
+                    .into_iter()
+                    .map(|position| (RegionStore::region_of(position), position)),
+            );
+        }
+
+        let mut stored_region = None;
+        let mut stored = HashMap::new();
+        for (region, position) in positions {
+            if let Some(chunk) = self.chunks.get(&position) {
+                visit(position, chunk)?;
+                continue;
+            }
+
+            let Some(regions) = &self.regions else {
+                continue;
+            };
+            if stored_region != Some(region) {
+                stored = regions.load_region(region)?.into_iter().collect();
+                stored_region = Some(region);
+            }
+            if let Some(chunk) = stored.get(&position) {
+                visit(position, chunk)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn stream(&mut self, center: (i32, i32), radius: usize) -> io::Result<()> {
+        if self.regions.is_none() {
+            return Ok(());
+        }
+
+        let (center_x, center_y) = Self::chunk_position(center.0, center.1).0;
+        let radius = radius as i32;
+        let near = |(chunk_x, chunk_y): (i32, i32), distance: i32| {
+            (chunk_x - center_x).abs() <= distance && (chunk_y - center_y).abs() <= distance
+        };
+
+        self.evict_chunks(|position| !near(position, radius + 1))?;
+        self.load_chunks((center_y - radius..=center_y + radius).flat_map(|chunk_y| {
+            (center_x - radius..=center_x + radius).map(move |chunk_x| (chunk_x, chunk_y))
+        }))
+    }
+
+    pub fn set_loaded(&mut self, loaded: &[(i32, i32)]) -> io::Result<()> {
+        if self.regions.is_none() {
+            return Ok(());
+        }
+
+        self.evict_chunks(|position| !loaded.contains(&position))?;
+        self.load_chunks(loaded.iter().copied())
+    }
+
+    fn evict_chunks(&mut self, evict: impl Fn((i32, i32)) -> bool) -> io::Result<()> {
+        let Some(regions) = &self.regions else {
+            return Ok(());
+        };
+
+        let evicted: Vec<_> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|&position| evict(position))
+            .collect();
+        if evicted.is_empty() {
+            return Ok(());
+        }
+
+        regions.save_chunks(
+            evicted
+                .iter()
+                .map(|position| (*position, &self.chunks[position])),
+        )?;
+        for position in evicted {
+            self.chunks.remove(&position);
+        }
+
+        Ok(())
+    }
+
+    fn load_chunks(&mut self, positions: impl Iterator<Item = (i32, i32)>) -> io::Result<()> {
+        let Some(regions) = &self.regions else {
+            return Ok(());
+        };
+
+        let missing: Vec<_> = positions
+            .filter(|position| !self.chunks.contains_key(position))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+
+        let mut stored: HashMap<_, _> = regions
+            .load_chunks(missing.iter().copied())?
+            .into_iter()
+            .collect();
+        for position in missing {
+            let chunk = stored.remove(&position).unwrap_or_default();
+            self.chunks.insert(position, chunk);
+        }
+
+        Ok(())
+    }
+
+    pub fn loaded_bounds(&self) -> Option<CellBounds> {
+        Self::chunks_bounds(self.chunks.keys().copied())
+    }
+
+    /// Returns the cells spanned by every chunk of the world, including the chunks of a
+    /// streamed world that are only stored in region files.
+    pub fn bounds(&self) -> io::Result<Option<CellBounds>> {
+        let mut positions = Vec::new();
+        self.visit_chunks(|position, _| {
+            positions.push(position);
+            Ok(())
+        })?;
+
+        Ok(Self::chunks_bounds(positions.into_iter()))
+    }
+
+    fn chunks_bounds(positions: impl Iterator<Item = (i32, i32)> + Clone) -> Option<CellBounds> {
+        let min_x = positions.clone().map(|(chunk_x, _)| chunk_x).min()?;
+        let min_y = positions.clone().map(|(_, chunk_y)| chunk_y).min()?;
+        let max_x = positions.clone().map(|(chunk_x, _)| chunk_x).max()?;
+        let max_y = positions.map(|(_, chunk_y)| chunk_y).max()?;
+        Some((
+            (min_x * CHUNK_WIDTH as i32, min_y * CHUNK_HEIGHT as i32),
+            (
+                (max_x + 1) * CHUNK_WIDTH as i32 - 1,
+                (max_y + 1) * CHUNK_HEIGHT as i32 - 1,
+            ),
+        ))
+    }
+
+    // A streamed world only simulates chunks surrounded by loaded chunks, so the unloaded
+    // area beyond them stays frozen instead of acting as a wall.
+    fn is_simulated(&self, (chunk_x, chunk_y): (i32, i32)) -> bool {
+        self.regions.is_none()
+            || (-1..=1).all(|dy| {
+                (-1..=1).all(|dx| self.chunks.contains_key(&(chunk_x + dx, chunk_y + dy)))
+            })
+    }
+
+    fn update_chunks(&mut self, mut chunks: Vec<(i32, i32)>, update_counter: usize, seed: u64) {
+        chunks.sort();
+
+        let (width, height) = (CHUNK_WIDTH as i32, CHUNK_HEIGHT as i32);
+        for column in chunks.chunk_by(|a, b| a.0 == b.0) {
+            let chunk_x = column[0].0;
+            for x in chunk_x * width..(chunk_x + 1) * width {
+                for &(_, chunk_y) in column {
+                    for y in chunk_y * height..(chunk_y + 1) * height {
+                        self.update_cell(x, y, update_counter, seed);
+                    }
+                }
+            }
+        }
+    }
+
+    fn chunk_position(x: i32, y: i32) -> ((i32, i32), usize, usize) {
+        let (width, height) = (CHUNK_WIDTH as i32, CHUNK_HEIGHT as i32);
+        (
+            (x.div_euclid(width), y.div_euclid(height)),
+            x.rem_euclid(width) as usize,
+            y.rem_euclid(height) as usize,
+        )
+    }
+}
+
+impl CellGrid for World {
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        self.chunks.contains_key(&Self::chunk_position(x, y).0)
+    }
+
+    fn get_cell(&self, x: i32, y: i32) -> Option<Cell> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+
+        let (position, local_x, local_y) = Self::chunk_position(x, y);
+        self.chunks[&position].cells[local_x][local_y]
+    }
+
+    fn set_cell(&mut self, x: i32, y: i32, cell: Option<Cell>) {
+        if !self.in_bounds(x, y) {
+            return;
+        }
+
+        let (position, local_x, local_y) = Self::chunk_position(x, y);
+        if let Some(chunk) = self.chunks.get_mut(&position) {
+            chunk.cells[local_x][local_y] = cell;
+        }
+    }
+}
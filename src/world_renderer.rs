@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::{
+    bbox::BoundingBox,
+    chunk::{CHUNK_HEIGHT, CHUNK_WIDTH},
+    render::{pipeline::RenderPipeline2D, sprite::Sprite},
+    world::World,
+};
+
+pub const CELL_SIZE: f32 = 2.0;
+
+pub struct WorldRenderer {
+    sprites: HashMap<(i32, i32), Sprite>,
+    pixels: Vec<u8>,
+}
+
+impl WorldRenderer {
+    pub fn new() -> Self {
+        Self {
+            sprites: HashMap::new(),
+            pixels: vec![0; CHUNK_WIDTH * CHUNK_HEIGHT * 4],
+        }
+    }
+
+    pub fn world_pos_to_cell(&self, world_pos: cgmath::Point2<f32>) -> (i32, i32) {
+        let x = world_pos.x / CELL_SIZE;
+        let y = -world_pos.y / CELL_SIZE;
+        (x.floor() as i32, y.floor() as i32)
+    }
+
+    pub fn cells_bbox(from: (i32, i32), to: (i32, i32)) -> BoundingBox {
+        BoundingBox {
+            min: (
+                from.0.min(to.0) as f32 * CELL_SIZE,
+                -(from.1.max(to.1) + 1) as f32 * CELL_SIZE,
+            )
+                .into(),
+            max: (
+                (from.0.max(to.0) + 1) as f32 * CELL_SIZE,
+                -from.1.min(to.1) as f32 * CELL_SIZE,
+            )
+                .into(),
+        }
+    }
+
+    pub fn visible_cells(&self, visible: &BoundingBox) -> ((i32, i32), (i32, i32)) {
+        let (min_x, max_y) = self.world_pos_to_cell(visible.min);
+        let (max_x, min_y) = self.world_pos_to_cell(visible.max);
+        ((min_x, min_y), (max_x, max_y))
+    }
+
+    pub fn upload(
+        &mut self,
+        render_pipeline: &RenderPipeline2D,
+        world: &World,
+        overlay: &[((i32, i32), [u8; 4])],
+        visible: &BoundingBox,
+    ) {
+        self.sprites
+            .retain(|&(chunk_x, chunk_y), _| world.is_loaded(chunk_x, chunk_y));
+
+        for ((chunk_x, chunk_y), chunk) in world.loaded_chunks() {
+            if !Self::chunk_bbox(chunk_x, chunk_y).intersects(visible) {
+                continue;
+            }
+
+            chunk.draw(&mut self.pixels);
+            Self::draw_overlay(
+                overlay,
+                (chunk_x * CHUNK_WIDTH as i32, chunk_y * CHUNK_HEIGHT as i32),
+                &mut self.pixels,
+            );
+            self.sprites
+                .entry((chunk_x, chunk_y))
+                .or_insert_with(|| Self::create_chunk_sprite(render_pipeline, chunk_x, chunk_y))
+                .upload_pixels(&self.pixels);
+        }
+    }
+
+    pub fn draw<'a: 'b, 'b>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'b>,
+        visible: &BoundingBox,
+    ) {
+        for (&(chunk_x, chunk_y), sprite) in &self.sprites {
+            if Self::chunk_bbox(chunk_x, chunk_y).intersects(visible) {
+                sprite.draw(render_pass);
+            }
+        }
+    }
+
+    fn chunk_bbox(chunk_x: i32, chunk_y: i32) -> BoundingBox {
+        let (width, height) = (CHUNK_WIDTH as i32, CHUNK_HEIGHT as i32);
+        Self::cells_bbox(
+            (chunk_x * width, chunk_y * height),
+            ((chunk_x + 1) * width - 1, (chunk_y + 1) * height - 1),
+        )
+    }
+
+    fn create_chunk_sprite(
+        render_pipeline: &RenderPipeline2D,
+        chunk_x: i32,
+        chunk_y: i32,
+    ) -> Sprite {
+        let chunk_quad_size = (
+            (CHUNK_WIDTH as f32 * CELL_SIZE) as usize,
+            (CHUNK_HEIGHT as f32 * CELL_SIZE) as usize,
+        );
+        let mut sprite =
+            render_pipeline.create_sprite((CHUNK_WIDTH, CHUNK_HEIGHT), chunk_quad_size);
+        let bbox = Self::chunk_bbox(chunk_x, chunk_y);
+        sprite.update_model((bbox.min.x, bbox.min.y, 0.0).into());
+        sprite
+    }
+
+    fn draw_overlay(
+        overlay: &[((i32, i32), [u8; 4])],
+        chunk_origin: (i32, i32),
+        pixels: &mut [u8],
+    ) {
+        for &((x, y), color) in overlay {
+            let (local_x, local_y) = (x - chunk_origin.0, y - chunk_origin.1);
+            if (0..CHUNK_WIDTH as i32).contains(&local_x)
+                && (0..CHUNK_HEIGHT as i32).contains(&local_y)
+            {
+                let start = (local_x as usize + local_y as usize * CHUNK_WIDTH) * 4;
+                pixels[start..start + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+impl Default for WorldRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}